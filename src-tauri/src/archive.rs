@@ -0,0 +1,442 @@
+//! Collects a project and the media it uses into one folder or zip for handoff
+//!
+//! Media is copied into a `media/` folder next to the project file and the project's
+//! paths are rewritten relative to it. With `trim_media`, only the span of each file
+//! the timeline uses (plus handles) is kept, transcoded by FFmpeg.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ClipforgeError, ErrorKind};
+use crate::export::plan::codec_args;
+use crate::project::{self, Project};
+use crate::{MediaKind, VideoMetadata};
+
+/// Seconds kept before and after each used range when trimming
+pub const DEFAULT_HANDLE_SECONDS: f64 = 1.0;
+
+const MEDIA_DIR: &str = "media";
+
+/// How to collect a project
+#[derive(Debug, Deserialize, Clone)]
+pub struct ArchiveOptions {
+    /// Folder to create, or the `.zip` file to write when `zip` is set
+    pub destination: String,
+    #[serde(default)]
+    pub zip: bool,
+    /// Keep only the parts of each file the timeline uses, dropping unused media
+    #[serde(default)]
+    pub trim_media: bool,
+    #[serde(default = "default_handle_seconds")]
+    pub handle_seconds: f64,
+}
+
+fn default_handle_seconds() -> f64 {
+    DEFAULT_HANDLE_SECONDS
+}
+
+/// A media file to put in the archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveFile {
+    pub media_id: String,
+    pub source: PathBuf,
+    /// Path inside the archive, with `/` separators
+    pub relative_path: String,
+    pub media_kind: MediaKind,
+    /// Seconds of the source to keep, None to copy the whole file
+    pub range: Option<(f64, f64)>,
+}
+
+/// The rewritten project and the files to collect for it
+#[derive(Debug, Clone)]
+pub struct ArchivePlan {
+    pub project: Project,
+    pub files: Vec<ArchiveFile>,
+}
+
+/// Where the archive was written
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveResult {
+    pub path: String,
+    pub media_files: usize,
+    pub total_bytes: u64,
+}
+
+/// Decides which files to collect and rewrites the project to point at them
+pub fn plan_archive(project: &Project, trim_media: bool, handle_seconds: f64) -> ArchivePlan {
+    // Span of each media entry used by the timeline
+    let mut used: HashMap<&str, (f64, f64)> = HashMap::new();
+    for clip in &project.clips {
+        let span = used.entry(clip.clip_id.as_str()).or_insert((clip.trim_start, clip.trim_end));
+        span.0 = span.0.min(clip.trim_start);
+        span.1 = span.1.max(clip.trim_end);
+    }
+
+    let mut archived = project.clone();
+    archived.media.clear();
+    // Export destinations point into the sender's file system
+    archived.export_settings.output_path = None;
+
+    let mut names = HashSet::new();
+    let mut files = Vec::new();
+    let mut offsets = HashMap::new();
+    for media in &project.media {
+        let span = used.get(media.id.as_str()).copied();
+        if trim_media && span.is_none() {
+            continue;
+        }
+
+        let metadata = &media.metadata;
+        let range = span
+            .filter(|_| trim_media && metadata.media_kind != MediaKind::Image)
+            .map(|(start, end)| {
+                let end = end + handle_seconds;
+                let end = if metadata.duration > 0.0 { end.min(metadata.duration) } else { end };
+                ((start - handle_seconds).max(0.0), end)
+            })
+            .filter(|(start, end)| *start > 0.0 || *end < metadata.duration);
+
+        let source = PathBuf::from(&metadata.path);
+        let filename = match range {
+            // Trimmed files are re-encoded, so they get the container of the encoders used
+            Some(_) => Path::new(&metadata.filename)
+                .with_extension(if metadata.media_kind == MediaKind::Audio { "m4a" } else { "mp4" })
+                .to_string_lossy()
+                .to_string(),
+            None => metadata.filename.clone(),
+        };
+        let relative_path = format!("{}/{}", MEDIA_DIR, unique_name(&mut names, &filename));
+
+        let mut archived_media = media.clone();
+        archived_media.metadata.path = relative_path.clone();
+        if let Some((start, end)) = range {
+            archived_media.metadata.duration = end - start;
+            offsets.insert(media.id.clone(), start);
+        }
+        archived.media.push(archived_media);
+        files.push(ArchiveFile {
+            media_id: media.id.clone(),
+            source,
+            relative_path,
+            media_kind: metadata.media_kind,
+            range,
+        });
+    }
+
+    // Trimmed files start later in the source, so clip in/out points move back
+    for clip in &mut archived.clips {
+        if let Some(offset) = offsets.get(&clip.clip_id) {
+            clip.trim_start -= offset;
+            clip.trim_end -= offset;
+        }
+    }
+
+    ArchivePlan {
+        project: archived,
+        files,
+    }
+}
+
+/// FFmpeg arguments that cut `range` out of a file, re-encoding it so the cut is frame accurate
+pub fn trim_args(file: &ArchiveFile, output: &Path, video_encoder: &str, audio_encoder: &str) -> Vec<String> {
+    let (start, end) = file.range.unwrap_or((0.0, 0.0));
+    let mut args: Vec<String> = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        format!("{:.3}", start),
+        "-i".to_string(),
+        file.source.to_string_lossy().to_string(),
+        "-t".to_string(),
+        format!("{:.3}", end - start),
+    ];
+
+    // Keep every audio stream so clips' audio_stream choices still apply
+    let codecs = codec_args(video_encoder, audio_encoder);
+    if file.media_kind == MediaKind::Audio {
+        let audio_start = codecs.iter().position(|arg| arg == "-c:a").unwrap_or(0);
+        args.extend(["-map", "0:a", "-vn"].map(String::from));
+        args.extend(codecs[audio_start..].iter().cloned());
+    } else {
+        args.extend(["-map", "0:v:0", "-map", "0:a?"].map(String::from));
+        args.extend(codecs);
+    }
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+/// Copies or trims every file of the plan and writes the project next to them
+/// `trim` runs FFmpeg for one file; `probe` re-reads the metadata of trimmed files
+/// Nothing is overwritten: an existing zip or non-empty folder is refused
+pub fn write_archive<T, P>(
+    plan: &ArchivePlan,
+    options: &ArchiveOptions,
+    trim: T,
+    probe: P,
+) -> Result<ArchiveResult, ClipforgeError>
+where
+    T: Fn(&ArchiveFile, &Path) -> Result<(), ClipforgeError>,
+    P: Fn(&str) -> Result<VideoMetadata, ClipforgeError>,
+{
+    let destination = PathBuf::from(&options.destination);
+    let folder = if options.zip { destination.with_extension("staging") } else { destination.clone() };
+    if options.zip && destination.exists() {
+        return Err(already_exists(&destination, "already exists"));
+    }
+    if folder.exists() && fs::read_dir(&folder).map_or(true, |mut entries| entries.next().is_some()) {
+        return Err(already_exists(&folder, "already exists and is not empty"));
+    }
+    fs::create_dir_all(folder.join(MEDIA_DIR)).map_err(|e| ClipforgeError::io("Failed to create", &folder, e))?;
+
+    let result = collect(plan, &folder, &destination, trim, probe).and_then(|project_file| {
+        if options.zip {
+            zip_folder(&folder, &destination, &project_file, plan)?;
+        }
+        Ok(project_file)
+    });
+    if options.zip || result.is_err() {
+        let _ = fs::remove_dir_all(&folder);
+    }
+    result?;
+
+    let archive_path = if options.zip { destination.clone() } else { folder.clone() };
+    let total_bytes = if options.zip {
+        fs::metadata(&destination).map(|m| m.len()).unwrap_or(0)
+    } else {
+        plan.files
+            .iter()
+            .filter_map(|file| fs::metadata(folder.join(&file.relative_path)).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    };
+    Ok(ArchiveResult {
+        path: archive_path.to_string_lossy().to_string(),
+        media_files: plan.files.len(),
+        total_bytes,
+    })
+}
+
+fn already_exists(path: &Path, problem: &str) -> ClipforgeError {
+    ClipforgeError::new(ErrorKind::InvalidInput, format!("{} {}", path.display(), problem)).with_path(path)
+}
+
+/// Fills `folder` with the media and project file; returns the project file's name
+fn collect<T, P>(
+    plan: &ArchivePlan,
+    folder: &Path,
+    destination: &Path,
+    trim: T,
+    probe: P,
+) -> Result<String, ClipforgeError>
+where
+    T: Fn(&ArchiveFile, &Path) -> Result<(), ClipforgeError>,
+    P: Fn(&str) -> Result<VideoMetadata, ClipforgeError>,
+{
+    let mut project = plan.project.clone();
+    for file in &plan.files {
+        let output = folder.join(&file.relative_path);
+        if file.range.is_none() {
+            fs::copy(&file.source, &output).map_err(|e| ClipforgeError::io("Failed to copy", &file.source, e))?;
+            continue;
+        }
+
+        trim(file, &output)?;
+        // Re-encoding can change the codec, bit rate and rotation, so describe the new file
+        let trimmed = probe(&output.to_string_lossy())?;
+        if let Some(media) = project.media.iter_mut().find(|media| media.id == file.media_id) {
+            let duration = media.metadata.duration;
+            media.metadata = VideoMetadata {
+                path: file.relative_path.clone(),
+                duration: if trimmed.duration > 0.0 { trimmed.duration } else { duration },
+                ..trimmed
+            };
+        }
+    }
+
+    let stem = destination
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string());
+    let project_file = format!("{}.{}", stem, project::PROJECT_EXTENSION);
+    project::save(&folder.join(&project_file), &project)?;
+    Ok(project_file)
+}
+
+/// Zips the project file and media; media is stored uncompressed since it already is
+fn zip_folder(folder: &Path, zip_path: &Path, project_file: &str, plan: &ArchivePlan) -> Result<(), ClipforgeError> {
+    use zip::write::SimpleFileOptions;
+    use zip::CompressionMethod;
+
+    let zip_err = |e: zip::result::ZipError| {
+        ClipforgeError::new(ErrorKind::Io, format!("Failed to write {}: {}", zip_path.display(), e)).with_path(zip_path)
+    };
+    // create_new so a zip that appeared since write_archive checked is not truncated
+    let file = fs::File::create_new(zip_path).map_err(|e| ClipforgeError::io("Failed to create", zip_path, e))?;
+    let mut zip = zip::ZipWriter::new(file);
+
+    let entries = std::iter::once((project_file, CompressionMethod::Deflated)).chain(
+        plan.files
+            .iter()
+            .map(|file| (file.relative_path.as_str(), CompressionMethod::Stored)),
+    );
+    for (name, compression) in entries {
+        let options = SimpleFileOptions::default().compression_method(compression).large_file(true);
+        zip.start_file(name, options).map_err(zip_err)?;
+        let source_path = folder.join(name);
+        let mut source = fs::File::open(&source_path).map_err(|e| ClipforgeError::io("Failed to read", &source_path, e))?;
+        io::copy(&mut source, &mut zip).map_err(|e| ClipforgeError::io("Failed to write", zip_path, e))?;
+    }
+    zip.finish().map_err(zip_err)?;
+    Ok(())
+}
+
+/// `name`, or `name-2`, `name-3`, ... if it is already taken (case-insensitively)
+fn unique_name(taken: &mut HashSet<String>, name: &str) -> String {
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    let mut candidate = name.to_string();
+    let mut counter = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{}-{}{}", stem, counter, extension);
+        counter += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, media, temp_dir};
+    use crate::TimelineClip;
+
+    fn clip(clip_id: &str, trim_start: f64, trim_end: f64) -> TimelineClip {
+        let id = format!("timeline-{}-{}", clip_id, trim_start);
+        test_support::clip(&id, clip_id, 0, 0.0, trim_start, trim_end)
+    }
+
+    fn project() -> Project {
+        Project {
+            media: vec![
+                media("a", "/day1/take.mp4", MediaKind::Video, 60.0),
+                media("b", "/day2/take.mp4", MediaKind::Video, 10.0),
+                media("c", "/music/bed.mp3", MediaKind::Audio, 180.0),
+                media("d", "/unused/extra.mov", MediaKind::Video, 30.0),
+            ],
+            clips: vec![
+                clip("a", 10.0, 20.0),
+                clip("a", 30.0, 35.0),
+                clip("b", 0.5, 9.8),
+                clip("c", 0.0, 45.0),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn copies_everything_with_relative_paths() {
+        let plan = plan_archive(&project(), false, DEFAULT_HANDLE_SECONDS);
+
+        let paths: Vec<&str> = plan.files.iter().map(|file| file.relative_path.as_str()).collect();
+        assert_eq!(paths, ["media/take.mp4", "media/take-2.mp4", "media/bed.mp3", "media/extra.mov"]);
+        assert!(plan.files.iter().all(|file| file.range.is_none()));
+        assert_eq!(plan.project.media[1].metadata.path, "media/take-2.mp4");
+        assert_eq!(plan.project.clips[0].trim_start, 10.0);
+    }
+
+    #[test]
+    fn trims_used_ranges_with_handles() {
+        let plan = plan_archive(&project(), true, DEFAULT_HANDLE_SECONDS);
+
+        let ranges: Vec<(&str, Option<(f64, f64)>)> =
+            plan.files.iter().map(|file| (file.relative_path.as_str(), file.range)).collect();
+        assert_eq!(
+            ranges,
+            [
+                ("media/take.mp4", Some((9.0, 36.0))),
+                // Handles reach both ends of the file, so it is copied whole
+                ("media/take-2.mp4", None),
+                ("media/bed.m4a", Some((0.0, 46.0))),
+            ]
+        );
+
+        let project = &plan.project;
+        assert_eq!(project.media.len(), 3, "unused media is dropped");
+        assert_eq!(project.media[0].metadata.duration, 27.0);
+        assert_eq!((project.clips[0].trim_start, project.clips[0].trim_end), (1.0, 11.0));
+        assert_eq!((project.clips[1].trim_start, project.clips[1].trim_end), (21.0, 26.0));
+        assert_eq!((project.clips[2].trim_start, project.clips[2].trim_end), (0.5, 9.8));
+    }
+
+    #[test]
+    fn builds_trim_commands() {
+        let plan = plan_archive(&project(), true, DEFAULT_HANDLE_SECONDS);
+
+        let video = trim_args(&plan.files[0], Path::new("/out/media/take.mp4"), "libx264", "aac");
+        assert_eq!(
+            video.join(" "),
+            "-y -ss 9.000 -i /day1/take.mp4 -t 27.000 -map 0:v:0 -map 0:a? \
+             -c:v libx264 -preset medium -crf 23 -c:a aac -b:a 192k /out/media/take.mp4"
+        );
+
+        let audio = trim_args(&plan.files[2], Path::new("/out/media/bed.m4a"), "libx264", "aac");
+        assert_eq!(
+            audio.join(" "),
+            "-y -ss 0.000 -i /music/bed.mp3 -t 46.000 -map 0:a -vn -c:a aac -b:a 192k /out/media/bed.m4a"
+        );
+    }
+
+    #[test]
+    fn writes_a_folder_and_a_zip() {
+        let root = temp_dir("archive");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/take.mp4"), b"video bytes").unwrap();
+
+        let project = Project {
+            media: vec![media("a", &root.join("src/take.mp4").to_string_lossy(), MediaKind::Video, 5.0)],
+            clips: vec![clip("a", 0.0, 5.0)],
+            ..Default::default()
+        };
+        let plan = plan_archive(&project, false, DEFAULT_HANDLE_SECONDS);
+        let no_trim = |_: &ArchiveFile, _: &Path| -> Result<(), ClipforgeError> { unreachable!() };
+        let no_probe = |_: &str| -> Result<VideoMetadata, ClipforgeError> { unreachable!() };
+
+        let options = ArchiveOptions {
+            destination: root.join("Handoff").to_string_lossy().to_string(),
+            zip: false,
+            trim_media: false,
+            handle_seconds: DEFAULT_HANDLE_SECONDS,
+        };
+        let result = write_archive(&plan, &options, no_trim, no_probe).unwrap();
+        assert_eq!(result.total_bytes, 11);
+        assert_eq!(fs::read(root.join("Handoff/media/take.mp4")).unwrap(), b"video bytes");
+        let reopened = project::load(&root.join("Handoff/Handoff.clipforge")).unwrap();
+        assert_eq!(
+            PathBuf::from(&reopened.media[0].metadata.path),
+            root.join("Handoff").join("media/take.mp4")
+        );
+        let refused = write_archive(&plan, &options, no_trim, no_probe).unwrap_err();
+        assert_eq!(refused.kind, ErrorKind::InvalidInput, "refuses to overwrite a folder");
+
+        let zip_options = ArchiveOptions {
+            destination: root.join("Handoff.zip").to_string_lossy().to_string(),
+            zip: true,
+            ..options
+        };
+        write_archive(&plan, &zip_options, no_trim, no_probe).unwrap();
+        let archive = zip::ZipArchive::new(fs::File::open(root.join("Handoff.zip")).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["Handoff.clipforge", "media/take.mp4"]);
+        assert!(!root.join("Handoff.staging").exists());
+
+        let refused = write_archive(&plan, &zip_options, no_trim, no_probe).unwrap_err();
+        assert_eq!(refused.kind, ErrorKind::InvalidInput, "refuses to overwrite a zip");
+        assert!(fs::metadata(root.join("Handoff.zip")).unwrap().len() > 0);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,240 @@
+//! Periodic project snapshots and crash recovery
+//!
+//! A session lock file exists while the app runs and is removed on a clean exit. If it is
+//! still there at startup, the last session crashed and its newest snapshot is offered
+//! for recovery. Snapshots taken earlier in the current session are offered too, which
+//! covers webview reloads that wipe the frontend's state while the backend keeps running.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs_util::write_atomically;
+use crate::project::Project;
+
+/// Snapshots kept on disk; older ones are deleted as new ones are written
+const MAX_SNAPSHOTS: usize = 10;
+
+const LOCK_FILE: &str = "session.lock";
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+
+/// An autosaved copy of the project
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    /// Milliseconds since the Unix epoch
+    pub saved_at: u64,
+    /// Project file the snapshot belongs to, None if it was never saved
+    pub project_path: Option<String>,
+    pub project: Project,
+}
+
+/// Snapshot store and session lock, managed as Tauri state
+pub struct Autosave {
+    dir: PathBuf,
+    state: Mutex<AutosaveState>,
+}
+
+struct AutosaveState {
+    /// Whether the previous session ended without removing its lock
+    unclean_shutdown: bool,
+    /// Serialized project of the last snapshot, to skip writing unchanged projects
+    last_json: Option<String>,
+    /// Time of this session's first snapshot since startup or the last discard
+    first_saved_at: Option<u64>,
+}
+
+impl Autosave {
+    /// Takes the session lock in `dir`, noting whether the last session left it behind
+    pub fn start(dir: PathBuf) -> Self {
+        let lock_path = dir.join(LOCK_FILE);
+        let unclean_shutdown = lock_path.exists();
+        if unclean_shutdown {
+            log::warn!("Previous session did not shut down cleanly");
+        }
+
+        let started_at = now_millis().to_string();
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&lock_path, started_at)) {
+            log::error!("Failed to write session lock {}: {}", lock_path.display(), e);
+        }
+
+        Self {
+            dir,
+            state: Mutex::new(AutosaveState {
+                unclean_shutdown,
+                last_json: None,
+                first_saved_at: None,
+            }),
+        }
+    }
+
+    /// Writes a snapshot unless the project is unchanged since the last one
+    /// Returns whether a snapshot was written
+    pub fn save(&self, project: &Project, project_path: Option<String>) -> Result<bool, String> {
+        let project_json = serde_json::to_string(project)
+            .map_err(|e| format!("Failed to serialize project: {}", e))?;
+        let mut state = self.state.lock().unwrap();
+        if state.last_json.as_deref() == Some(project_json.as_str()) {
+            return Ok(false);
+        }
+
+        // Never reuse a timestamp, so two saves in the same millisecond keep both snapshots
+        let newest = self.snapshot_paths().first().and_then(|path| snapshot_time(path));
+        let saved_at = now_millis().max(newest.map_or(0, |time| time + 1));
+        let snapshot = Snapshot {
+            saved_at,
+            project_path,
+            project: project.clone(),
+        };
+        let json = serde_json::to_string(&snapshot)
+            .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create autosave directory: {}", e))?;
+        let path = self.dir.join(format!("{}{}.json", SNAPSHOT_PREFIX, saved_at));
+        write_atomically(&path, json).map_err(|e| format!("Failed to write snapshot: {}", e))?;
+        state.last_json = Some(project_json);
+        state.first_saved_at.get_or_insert(saved_at);
+
+        for old in self.snapshot_paths().iter().skip(MAX_SNAPSHOTS) {
+            let _ = fs::remove_file(old);
+        }
+        Ok(true)
+    }
+
+    /// Newest readable snapshot worth offering: any after a crash, otherwise only ones
+    /// from this session
+    pub fn recovery(&self) -> Option<Snapshot> {
+        let state = self.state.lock().unwrap();
+        self.snapshot_paths()
+            .iter()
+            .filter(|path| {
+                state.unclean_shutdown
+                    || snapshot_time(path).zip(state.first_saved_at).is_some_and(|(time, first)| time >= first)
+            })
+            .find_map(|path| {
+                let json = fs::read_to_string(path).ok()?;
+                serde_json::from_str(&json)
+                    .inspect_err(|e| log::warn!("Skipping unreadable snapshot {}: {}", path.display(), e))
+                    .ok()
+            })
+    }
+
+    /// Stops offering the current snapshots for recovery; later ones are offered again
+    /// if the webview reloads
+    pub fn discard_recovery(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.unclean_shutdown = false;
+        for path in self.snapshot_paths() {
+            let _ = fs::remove_file(path);
+        }
+        state.last_json = None;
+        state.first_saved_at = None;
+    }
+
+    /// Releases the session lock on a clean exit
+    pub fn finish(&self) {
+        let _ = fs::remove_file(self.dir.join(LOCK_FILE));
+    }
+
+    /// Snapshot files, newest first
+    fn snapshot_paths(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<(u64, PathBuf)> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter_map(|path| snapshot_time(&path).map(|time| (time, path)))
+            .collect();
+        paths.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+        paths.into_iter().map(|(_, path)| path).collect()
+    }
+}
+
+/// Save time encoded in a snapshot's file name, `snapshot-<millis>.json`
+fn snapshot_time(path: &Path) -> Option<u64> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(".json")?
+        .parse()
+        .ok()
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::ProjectMedia;
+    use crate::test_support::temp_dir;
+    use crate::VideoMetadata;
+
+    fn project(media_count: usize) -> Project {
+        Project {
+            media: (0..media_count)
+                .map(|i| ProjectMedia {
+                    id: format!("clip-{}", i),
+                    metadata: VideoMetadata {
+                        path: format!("/videos/{}.mp4", i),
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn skips_unchanged_projects_and_rotates_snapshots() {
+        let dir = temp_dir("rotate");
+        let autosave = Autosave::start(dir.clone());
+
+        assert!(autosave.save(&project(1), None).unwrap());
+        assert!(!autosave.save(&project(1), None).unwrap());
+        for count in 2..=MAX_SNAPSHOTS + 3 {
+            assert!(autosave.save(&project(count), None).unwrap());
+        }
+
+        assert_eq!(autosave.snapshot_paths().len(), MAX_SNAPSHOTS);
+        let newest = autosave.recovery().unwrap();
+        assert_eq!(newest.project.media.len(), MAX_SNAPSHOTS + 3);
+    }
+
+    #[test]
+    fn offers_snapshots_after_an_unclean_shutdown() {
+        let dir = temp_dir("crash");
+        let crashed = Autosave::start(dir.clone());
+        crashed.save(&project(2), Some("/projects/demo.clipforge".to_string())).unwrap();
+        // No finish(): the lock file is left behind
+
+        let restarted = Autosave::start(dir.clone());
+        let snapshot = restarted.recovery().unwrap();
+        assert_eq!(snapshot.project.media.len(), 2);
+        assert_eq!(snapshot.project_path.as_deref(), Some("/projects/demo.clipforge"));
+
+        restarted.discard_recovery();
+        assert!(restarted.recovery().is_none());
+    }
+
+    #[test]
+    fn ignores_old_snapshots_after_a_clean_exit() {
+        let dir = temp_dir("clean");
+        let previous = Autosave::start(dir.clone());
+        previous.save(&project(1), None).unwrap();
+        previous.finish();
+
+        let restarted = Autosave::start(dir.clone());
+        assert!(restarted.recovery().is_none());
+
+        // Snapshots from this session are offered, e.g. after a webview reload
+        restarted.save(&project(3), None).unwrap();
+        assert_eq!(restarted.recovery().unwrap().project.media.len(), 3);
+    }
+}
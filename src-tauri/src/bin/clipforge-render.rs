@@ -0,0 +1,199 @@
+//! Headless renderer: exports a project file or an `ExportRequest` JSON through the same
+//! pipeline as the app, printing progress to the terminal
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+
+use clipforge_lib::error::{ClipforgeError, ErrorKind};
+use clipforge_lib::export::{self, ExportEvent, ExportJob, ExportJobs, ExportOutcome};
+use clipforge_lib::ffmpeg_capabilities::CapabilityCache;
+use clipforge_lib::ffmpeg_locator::{FfmpegLocator, Tool, ToolLocator};
+use clipforge_lib::{media, project, ExportRequest, VideoMetadata};
+use ffmpeg_next as ffmpeg;
+
+const USAGE: &str = "\
+Usage: clipforge-render <project.clipforge | request.json> [options]
+
+Options:
+  -o, --output <PATH>        Output file (defaults to the project's last export destination)
+      --format <FORMAT>      mp4, webm or mov
+      --resolution <RES>     Source, 720p, 1080p, 1440p or 4K
+      --ffmpeg <PATH>        FFmpeg binary to use instead of the bundled one or PATH
+                             (FFprobe is expected beside it)
+  -h, --help                 Show this help";
+
+#[derive(Default)]
+struct Args {
+    input: PathBuf,
+    output: Option<String>,
+    format: Option<String>,
+    resolution: Option<String>,
+    ffmpeg: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match render(args) {
+        Ok(output_path) => {
+            eprintln!("Exported {}", output_path);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parses the command line; None when help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => parsed.output = Some(value(&arg)?),
+            "--format" => parsed.format = Some(value(&arg)?),
+            "--resolution" => parsed.resolution = Some(value(&arg)?),
+            "--ffmpeg" => parsed.ffmpeg = Some(PathBuf::from(value(&arg)?)),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ if input.is_some() => return Err(format!("Unexpected argument: {}", arg)),
+            _ => input = Some(PathBuf::from(arg)),
+        }
+    }
+    parsed.input = input.ok_or("Missing project or export request file")?;
+    Ok(Some(parsed))
+}
+
+/// Renders the export and waits for FFmpeg to finish, returning the output path
+fn render(args: Args) -> Result<String, ClipforgeError> {
+    ffmpeg::init()
+        .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Failed to initialize FFmpeg: {}", e)))?;
+
+    let (request, clips_data) = load_request(&args)?;
+    let locator = match args.ffmpeg {
+        Some(ffmpeg) => {
+            let locator = FfmpegLocator::standalone().with_override(ffmpeg.clone())?;
+            // Fail here rather than partway through preparing the export
+            locator
+                .locate(Tool::Ffmpeg)?
+                .run(["-version"], &format!("{} is not a working FFmpeg binary", ffmpeg.display()))?;
+            locator
+        }
+        None => FfmpegLocator::standalone(),
+    };
+    let (plan, ffmpeg_path) =
+        export::prepare_export(&request, &clips_data, &locator, &CapabilityCache::default())?;
+
+    let job = ExportJob {
+        ffmpeg_path,
+        plan,
+        output_path: request.output_path.clone(),
+        log_dir: None,
+    };
+    let (sender, receiver) = mpsc::channel();
+    let reservation = ExportJobs::default().reserve(export::new_job_id())?;
+    export::start_export(reservation, job, print_progress, move |outcome| {
+        let _ = sender.send(outcome);
+    })?;
+
+    let outcome = receiver
+        .recv()
+        .map_err(|_| ClipforgeError::new(ErrorKind::Internal, "FFmpeg stopped without reporting a result"))?;
+    match outcome {
+        ExportOutcome::Completed => Ok(request.output_path),
+        ExportOutcome::Failed(failure) => {
+            for line in &failure.stderr_tail {
+                eprintln!("  {}", line);
+            }
+            eprintln!("Command: {}", failure.command_line);
+            Err(ClipforgeError::new(ErrorKind::FfmpegFailed, failure.message))
+        }
+        ExportOutcome::Cancelled => Err(ClipforgeError::new(ErrorKind::FfmpegFailed, "FFmpeg was stopped")),
+    }
+}
+
+/// Reads the input as a project file (by extension) or an export request, then applies
+/// the command-line overrides
+fn load_request(args: &Args) -> Result<(ExportRequest, Vec<VideoMetadata>), ClipforgeError> {
+    let is_project = args
+        .input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(project::PROJECT_EXTENSION));
+
+    let (mut request, clips_data) = if is_project {
+        project::load(&args.input)?.export_request(args.output.clone())?
+    } else {
+        let json = fs::read_to_string(&args.input).map_err(|e| ClipforgeError::io("Failed to read", &args.input, e))?;
+        let mut request: ExportRequest = serde_json::from_str(&json).map_err(|e| {
+            ClipforgeError::new(ErrorKind::InvalidInput, format!("Invalid export request: {}", e)).with_path(&args.input)
+        })?;
+        if let Some(output) = &args.output {
+            request.output_path = output.clone();
+        }
+        let clips_data = probe_sources(&request)?;
+        (request, clips_data)
+    };
+
+    if let Some(format) = &args.format {
+        request.format = format.clone();
+    }
+    if let Some(resolution) = &args.resolution {
+        request.resolution = resolution.clone();
+    }
+    Ok((request, clips_data))
+}
+
+/// Metadata for each source an export request refers to by path
+/// Missing files are left for `prepare_export` to report
+fn probe_sources(request: &ExportRequest) -> Result<Vec<VideoMetadata>, ClipforgeError> {
+    let mut clips_data: Vec<VideoMetadata> = Vec::new();
+    for clip in &request.clips {
+        if clips_data.iter().any(|c| c.path == clip.clip_id) {
+            continue;
+        }
+        let metadata = if Path::new(&clip.clip_id).is_file() {
+            media::extract_video_metadata(&clip.clip_id).map_err(|e| {
+                ClipforgeError::new(ErrorKind::UnsupportedMedia, format!("Failed to read {}: {}", clip.clip_id, e))
+                    .with_path(&clip.clip_id)
+            })?
+        } else {
+            VideoMetadata {
+                path: clip.clip_id.clone(),
+                ..Default::default()
+            }
+        };
+        clips_data.push(metadata);
+    }
+    Ok(clips_data)
+}
+
+/// Prints progress on a single, rewritten terminal line
+fn print_progress(event: ExportEvent) {
+    let ExportEvent::Progress(progress) = event else {
+        return;
+    };
+    let eta = progress.eta.map_or_else(|| "--".to_string(), |eta| format!("{:.0}s", eta));
+    let speed = progress.speed.map_or_else(String::new, |speed| format!(" {:.2}x", speed));
+    eprint!(
+        "\r{:>3}% {:>8.1}s rendered, {:.0}s elapsed, ETA {}{}   ",
+        progress.percent, progress.out_time, progress.elapsed, eta, speed
+    );
+    if progress.percent == 100 {
+        eprintln!();
+    }
+}
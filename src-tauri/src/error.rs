@@ -0,0 +1,129 @@
+//! Errors returned by commands, classified so the frontend can react to each kind
+//! (e.g. offer relinking when a source file is missing)
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// What went wrong, serialized in snake_case
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// A file the operation needs does not exist
+    FileNotFound,
+    /// A file exists but can't be read as media
+    UnsupportedMedia,
+    /// A replacement file doesn't match the media it should stand in for
+    IncompatibleMedia,
+    /// The FFmpeg build lacks an encoder, muxer or filter the operation needs
+    UnsupportedCodec,
+    /// No FFmpeg binary could be found
+    FfmpegMissing,
+    /// FFmpeg ran but failed
+    FfmpegFailed,
+    /// A project file is malformed or was saved by a newer version
+    InvalidProject,
+    /// The request itself can't be carried out, e.g. an empty timeline or an unknown job
+    InvalidInput,
+    /// Reading or writing a file failed
+    Io,
+    /// Anything else
+    Internal,
+}
+
+/// Error payload of every command
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ClipforgeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// File the error is about
+    pub path: Option<String>,
+    /// What FFmpeg wrote to stderr, for FFmpeg failures
+    pub ffmpeg_stderr: Option<String>,
+}
+
+impl ClipforgeError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+            ffmpeg_stderr: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_string_lossy().to_string());
+        self
+    }
+
+    pub fn file_not_found(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::new(ErrorKind::FileNotFound, format!("File not found: {}", path.display())).with_path(path)
+    }
+
+    /// A failed file operation, described as "`action` `path`: `error`"
+    /// Errors for files that don't exist are classed as `FileNotFound`
+    pub fn io(action: &str, path: impl AsRef<Path>, error: io::Error) -> Self {
+        let path = path.as_ref();
+        let kind = match error.kind() {
+            io::ErrorKind::NotFound => ErrorKind::FileNotFound,
+            _ => ErrorKind::Io,
+        };
+        Self::new(kind, format!("{} {}: {}", action, path.display(), error)).with_path(path)
+    }
+
+    /// An FFmpeg run that exited unsuccessfully; the message ends with FFmpeg's last
+    /// stderr line and the full stderr is kept alongside
+    pub fn ffmpeg_failed(action: &str, stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr).trim().to_string();
+        let message = match stderr.lines().last() {
+            Some(line) => format!("{}: {}", action, line),
+            None => action.to_string(),
+        };
+        Self {
+            ffmpeg_stderr: Some(stderr),
+            ..Self::new(ErrorKind::FfmpegFailed, message)
+        }
+    }
+}
+
+impl fmt::Display for ClipforgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ClipforgeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_io_errors() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        let err = ClipforgeError::io("Failed to read", "/videos/intro.mp4", missing);
+        assert_eq!(err.kind, ErrorKind::FileNotFound);
+        assert_eq!(err.path.as_deref(), Some("/videos/intro.mp4"));
+        assert_eq!(err.message, "Failed to read /videos/intro.mp4: No such file or directory");
+
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied");
+        assert_eq!(ClipforgeError::io("Failed to write", "/out.mp4", denied).kind, ErrorKind::Io);
+    }
+
+    #[test]
+    fn keeps_ffmpeg_stderr() {
+        let stderr = b"Input #0, lavfi\nUnknown encoder 'libx265'\n";
+        let err = ClipforgeError::ffmpeg_failed("Failed to trim intro.mp4", stderr);
+        assert_eq!(err.kind, ErrorKind::FfmpegFailed);
+        assert_eq!(err.message, "Failed to trim intro.mp4: Unknown encoder 'libx265'");
+        assert_eq!(err.ffmpeg_stderr.as_deref(), Some("Input #0, lavfi\nUnknown encoder 'libx265'"));
+
+        let serialized = serde_json::to_value(&err).unwrap();
+        assert_eq!(serialized["kind"], "ffmpeg_failed");
+        assert_eq!(serialized["path"], serde_json::Value::Null);
+    }
+}
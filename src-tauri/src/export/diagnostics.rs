@@ -0,0 +1,167 @@
+//! Diagnostics for failed exports: recent FFmpeg stderr and the exact command line
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Number of stderr lines kept for error reports
+const STDERR_TAIL_LINES: usize = 40;
+
+/// Details of a failed export, sent with the `export_failed` event
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ExportFailure {
+    /// Human-readable summary, including the most relevant FFmpeg message
+    pub message: String,
+    pub exit_code: Option<i32>,
+    /// The FFmpeg invocation, quoted so it can be pasted into a shell
+    pub command_line: String,
+    /// The last non-progress lines FFmpeg wrote to stderr
+    pub stderr_tail: Vec<String>,
+    /// Full log of this export, for attaching to bug reports
+    pub log_path: Option<String>,
+}
+
+impl ExportFailure {
+    /// Builds a failure report for an FFmpeg process that exited unsuccessfully
+    pub fn from_exit(
+        exit_code: Option<i32>,
+        command_line: String,
+        stderr_tail: Vec<String>,
+        log_path: Option<String>,
+    ) -> Self {
+        let mut message = match exit_code {
+            Some(code) => format!("FFmpeg export failed with exit code {}", code),
+            // No exit code on Unix means the process was killed by a signal
+            None => "FFmpeg was terminated by a signal".to_string(),
+        };
+        if let Some(line) = most_relevant_line(&stderr_tail) {
+            message.push_str(": ");
+            message.push_str(line);
+        }
+        Self { message, exit_code, command_line, stderr_tail, log_path }
+    }
+
+    /// Builds a failure report for an error that happened outside FFmpeg
+    pub fn from_error(message: String, command_line: String, log_path: Option<String>) -> Self {
+        Self { message, exit_code: None, command_line, stderr_tail: Vec::new(), log_path }
+    }
+}
+
+/// Ring buffer of the most recent FFmpeg stderr lines
+#[derive(Debug)]
+pub struct StderrTail {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for StderrTail {
+    fn default() -> Self {
+        Self::with_capacity(STDERR_TAIL_LINES)
+    }
+}
+
+impl StderrTail {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { lines: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Records a line, dropping the oldest one once the buffer is full
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() || self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    pub fn into_lines(self) -> Vec<String> {
+        self.lines.into()
+    }
+}
+
+/// Picks the line most likely to explain a failure: the last one that reads like an error
+fn most_relevant_line(lines: &[String]) -> Option<&str> {
+    const MARKERS: &[&str] = &["error", "invalid", "no such", "not found", "unknown", "failed", "cannot", "unable"];
+    lines
+        .iter()
+        .rev()
+        .find(|line| {
+            let lower = line.to_lowercase();
+            MARKERS.iter().any(|marker| lower.contains(marker))
+        })
+        .or_else(|| lines.last())
+        .map(|line| line.as_str())
+}
+
+/// Renders a command line with POSIX shell quoting where needed
+pub fn format_command_line(program: &Path, args: &[String]) -> String {
+    std::iter::once(program.to_string_lossy().to_string())
+        .chain(args.iter().cloned())
+        .map(|arg| shell_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_tail_keeps_the_most_recent_lines() {
+        let mut tail = StderrTail::with_capacity(3);
+        for line in ["one", "", "two", "three", "four  "] {
+            tail.push(line);
+        }
+        assert_eq!(tail.into_lines(), vec!["two", "three", "four"]);
+    }
+
+    #[test]
+    fn failure_message_includes_the_relevant_ffmpeg_error() {
+        let tail = vec![
+            "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'a.mp4':".to_string(),
+            "[AVFilterGraph @ 0x1] No such filter: 'foo'".to_string(),
+            "Error initializing complex filters.".to_string(),
+            "Exiting normally, received signal 2.".to_string(),
+        ];
+        let failure = ExportFailure::from_exit(Some(1), "ffmpeg -y".to_string(), tail.clone(), None);
+        assert_eq!(
+            failure.message,
+            "FFmpeg export failed with exit code 1: Error initializing complex filters."
+        );
+        assert_eq!(failure.stderr_tail, tail);
+
+        let failure = ExportFailure::from_exit(Some(1), String::new(), vec!["something odd".to_string()], None);
+        assert!(failure.message.ends_with(": something odd"));
+        let failure = ExportFailure::from_exit(None, String::new(), Vec::new(), None);
+        assert_eq!(failure.message, "FFmpeg was terminated by a signal");
+    }
+
+    #[test]
+    fn command_line_is_shell_quoted() {
+        let args = vec![
+            "-i".to_string(),
+            "/Users/me/My Videos/clip's.mp4".to_string(),
+            "-filter_complex".to_string(),
+            "[0:v]null[v]; [0:a]anull[a]".to_string(),
+            "out.mp4".to_string(),
+        ];
+        assert_eq!(
+            format_command_line(Path::new("/usr/bin/ffmpeg"), &args),
+            "/usr/bin/ffmpeg -i '/Users/me/My Videos/clip'\\''s.mp4' -filter_complex '[0:v]null[v]; [0:a]anull[a]' out.mp4"
+        );
+    }
+}
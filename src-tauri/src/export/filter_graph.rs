@@ -0,0 +1,360 @@
+//! Typed builder for FFmpeg `-filter_complex` graphs
+//!
+//! A graph is a list of chains; each chain reads from labeled pads, runs a
+//! sequence of filters and writes to new labeled pads. The graph checks that
+//! every label is produced exactly once and consumed exactly once before it
+//! is rendered, so malformed graphs are caught before FFmpeg is spawned.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// A pad that links chains together
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pad {
+    /// A stream of an input file, e.g. `[2:v]` or `[0:a]`
+    Input { index: usize, stream: String },
+    /// An intermediate or final label, e.g. `[vbase]`
+    Label(String),
+}
+
+impl Pad {
+    /// The video stream of input file `index`
+    pub fn video(index: usize) -> Self {
+        Pad::Input { index, stream: "v".to_string() }
+    }
+
+    /// The audio stream of input file `index`
+    pub fn audio(index: usize) -> Self {
+        Pad::Input { index, stream: "a".to_string() }
+    }
+
+    /// A specific audio stream of input file `index`, e.g. `[0:a:1]` for its second audio stream
+    pub fn audio_stream(index: usize, stream: usize) -> Self {
+        Pad::Input { index, stream: format!("a:{}", stream) }
+    }
+
+    /// A named label
+    pub fn label(name: impl Into<String>) -> Self {
+        Pad::Label(name.into())
+    }
+}
+
+impl fmt::Display for Pad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pad::Input { index, stream } => write!(f, "[{}:{}]", index, stream),
+            Pad::Label(name) => write!(f, "[{}]", name),
+        }
+    }
+}
+
+/// A single filter, rendered as `name=arg1:arg2:key=value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    name: String,
+    args: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), args: Vec::new() }
+    }
+
+    /// Adds a positional argument
+    pub fn arg(mut self, value: impl fmt::Display) -> Self {
+        self.args.push(value.to_string());
+        self
+    }
+
+    /// Adds a `key=value` option
+    pub fn opt(mut self, key: &str, value: impl fmt::Display) -> Self {
+        self.args.push(format!("{}={}", key, value));
+        self
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.args.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}={}", self.name, self.args.join(":"))
+        }
+    }
+}
+
+/// A linear chain of filters from input pads to output pads
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chain {
+    inputs: Vec<Pad>,
+    filters: Vec<Filter>,
+    outputs: Vec<Pad>,
+}
+
+impl Chain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input(mut self, pad: Pad) -> Self {
+        self.inputs.push(pad);
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn filters(mut self, filters: impl IntoIterator<Item = Filter>) -> Self {
+        self.filters.extend(filters);
+        self
+    }
+
+    pub fn output(mut self, pad: Pad) -> Self {
+        self.outputs.push(pad);
+        self
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pad in &self.inputs {
+            write!(f, "{}", pad)?;
+        }
+        let filters: Vec<String> = self.filters.iter().map(|filter| filter.to_string()).collect();
+        write!(f, "{}", filters.join(","))?;
+        for pad in &self.outputs {
+            write!(f, "{}", pad)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems found while validating a graph
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterGraphError {
+    /// A chain has no filters
+    EmptyChain(usize),
+    /// A chain writes to an input file pad instead of a label
+    InvalidOutput(String),
+    /// Two chains write to the same label
+    DuplicateLabel(String),
+    /// A label is read but never written
+    UndefinedLabel(String),
+    /// A label is read by more than one chain
+    LabelConsumedTwice(String),
+    /// A label is written but never read or mapped to the output file
+    UnusedLabel(String),
+}
+
+impl fmt::Display for FilterGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterGraphError::EmptyChain(index) => write!(f, "filter chain {} has no filters", index),
+            FilterGraphError::InvalidOutput(pad) => write!(f, "filter chain writes to input pad {}", pad),
+            FilterGraphError::DuplicateLabel(pad) => write!(f, "label {} is produced more than once", pad),
+            FilterGraphError::UndefinedLabel(pad) => write!(f, "label {} is used but never produced", pad),
+            FilterGraphError::LabelConsumedTwice(pad) => write!(f, "label {} is consumed more than once", pad),
+            FilterGraphError::UnusedLabel(pad) => write!(f, "label {} is produced but never used", pad),
+        }
+    }
+}
+
+impl std::error::Error for FilterGraphError {}
+
+/// An FFmpeg filter graph made of chains
+#[derive(Debug, Clone, Default)]
+pub struct FilterGraph {
+    chains: Vec<Chain>,
+}
+
+impl FilterGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chain: Chain) {
+        self.chains.push(chain);
+    }
+
+    /// Names of every filter used in the graph, for checking them against the FFmpeg build
+    pub fn filter_names(&self) -> BTreeSet<String> {
+        self.chains
+            .iter()
+            .flat_map(|chain| chain.filters.iter())
+            .map(|filter| filter.name.clone())
+            .collect()
+    }
+
+    /// Validates the graph and renders it for `-filter_complex`
+    /// `outputs` are the labels that will be mapped to the output file with `-map`
+    pub fn render(&self, outputs: &[Pad]) -> Result<String, FilterGraphError> {
+        let mut produced: HashSet<&Pad> = HashSet::new();
+        for (index, chain) in self.chains.iter().enumerate() {
+            if chain.filters.is_empty() {
+                return Err(FilterGraphError::EmptyChain(index));
+            }
+            for pad in &chain.outputs {
+                if matches!(pad, Pad::Input { .. }) {
+                    return Err(FilterGraphError::InvalidOutput(pad.to_string()));
+                }
+                if !produced.insert(pad) {
+                    return Err(FilterGraphError::DuplicateLabel(pad.to_string()));
+                }
+            }
+        }
+
+        let mut consumed: HashMap<&Pad, usize> = HashMap::new();
+        let reads = self.chains.iter().flat_map(|chain| chain.inputs.iter()).chain(outputs);
+        for pad in reads {
+            // Input file streams are provided by `-i` arguments, not by chains
+            if let Pad::Input { .. } = pad {
+                continue;
+            }
+            if !produced.contains(pad) {
+                return Err(FilterGraphError::UndefinedLabel(pad.to_string()));
+            }
+            let count = consumed.entry(pad).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                return Err(FilterGraphError::LabelConsumedTwice(pad.to_string()));
+            }
+        }
+
+        for chain in &self.chains {
+            for pad in &chain.outputs {
+                if !consumed.contains_key(pad) {
+                    return Err(FilterGraphError::UnusedLabel(pad.to_string()));
+                }
+            }
+        }
+
+        Ok(self
+            .chains
+            .iter()
+            .map(|chain| chain.to_string())
+            .collect::<Vec<_>>()
+            .join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_filters_with_positional_and_named_args() {
+        let filter = Filter::new("scale").arg(1920).arg(1080).opt("force_original_aspect_ratio", "decrease");
+        assert_eq!(filter.to_string(), "scale=1920:1080:force_original_aspect_ratio=decrease");
+        assert_eq!(Filter::new("anull").to_string(), "anull");
+    }
+
+    #[test]
+    fn renders_chains_with_pads() {
+        let chain = Chain::new()
+            .input(Pad::video(0))
+            .filter(Filter::new("setsar").arg(1))
+            .filter(Filter::new("fps").arg(30))
+            .output(Pad::label("v0"));
+        assert_eq!(chain.to_string(), "[0:v]setsar=1,fps=30[v0]");
+        assert_eq!(Pad::audio_stream(2, 1).to_string(), "[2:a:1]");
+    }
+
+    #[test]
+    fn renders_graph_joined_by_semicolons() {
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::video(0)).filter(Filter::new("null")).output(Pad::label("a")));
+        graph.push(Chain::new().input(Pad::label("a")).filter(Filter::new("null")).output(Pad::label("out")));
+        assert_eq!(graph.render(&[Pad::label("out")]).unwrap(), "[0:v]null[a]; [a]null[out]");
+    }
+
+    #[test]
+    fn rejects_duplicate_labels() {
+        // The old overlay branch wrote [outv] twice
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::video(0)).filter(Filter::new("null")).output(Pad::label("outv")));
+        graph.push(
+            Chain::new()
+                .input(Pad::label("outv"))
+                .input(Pad::video(1))
+                .filter(Filter::new("overlay"))
+                .output(Pad::label("outv")),
+        );
+        assert_eq!(
+            graph.render(&[Pad::label("outv")]),
+            Err(FilterGraphError::DuplicateLabel("[outv]".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_labels() {
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::label("missing")).filter(Filter::new("null")).output(Pad::label("out")));
+        assert_eq!(
+            graph.render(&[Pad::label("out")]),
+            Err(FilterGraphError::UndefinedLabel("[missing]".to_string()))
+        );
+        let empty = FilterGraph::new();
+        assert_eq!(
+            empty.render(&[Pad::label("out")]),
+            Err(FilterGraphError::UndefinedLabel("[out]".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_labels_consumed_twice() {
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::video(0)).filter(Filter::new("null")).output(Pad::label("a")));
+        graph.push(Chain::new().input(Pad::label("a")).filter(Filter::new("null")).output(Pad::label("b")));
+        assert_eq!(
+            graph.render(&[Pad::label("a"), Pad::label("b")]),
+            Err(FilterGraphError::LabelConsumedTwice("[a]".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_unused_labels() {
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::video(0)).filter(Filter::new("null")).output(Pad::label("a")));
+        graph.push(Chain::new().input(Pad::audio(0)).filter(Filter::new("anull")).output(Pad::label("b")));
+        assert_eq!(
+            graph.render(&[Pad::label("a")]),
+            Err(FilterGraphError::UnusedLabel("[b]".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_empty_chains_and_input_outputs() {
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().input(Pad::video(0)).output(Pad::label("a")));
+        assert_eq!(graph.render(&[Pad::label("a")]), Err(FilterGraphError::EmptyChain(0)));
+
+        let mut graph = FilterGraph::new();
+        graph.push(Chain::new().filter(Filter::new("anullsrc")).output(Pad::audio(0)));
+        assert_eq!(
+            graph.render(&[]),
+            Err(FilterGraphError::InvalidOutput("[0:a]".to_string()))
+        );
+    }
+
+    #[test]
+    fn lists_each_filter_name_once() {
+        let mut graph = FilterGraph::new();
+        graph.push(
+            Chain::new()
+                .input(Pad::video(0))
+                .filter(Filter::new("scale").arg(1280).arg(-2))
+                .filter(Filter::new("setsar").arg(1))
+                .output(Pad::label("a")),
+        );
+        graph.push(
+            Chain::new()
+                .input(Pad::video(1))
+                .filter(Filter::new("scale").arg(640).arg(-2))
+                .output(Pad::label("b")),
+        );
+        let names: Vec<String> = graph.filter_names().into_iter().collect();
+        assert_eq!(names, ["scale", "setsar"]);
+    }
+}
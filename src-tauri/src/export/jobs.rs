@@ -0,0 +1,393 @@
+//! Background export jobs: spawning, tracking and cancelling FFmpeg renders
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::{ClipforgeError, ErrorKind};
+
+use super::diagnostics::{format_command_line, ExportFailure, StderrTail};
+use super::progress::{ExportProgress, ProgressParser};
+use super::ExportPlan;
+
+/// Payload of the `export_complete` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportCompleted {
+    pub job_id: String,
+    pub output_path: String,
+}
+
+/// Payload of the `export_failed` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportFailed {
+    pub job_id: String,
+    #[serde(flatten)]
+    pub failure: ExportFailure,
+}
+
+/// Payload of the `export_cancelled` event
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportCancelled {
+    pub job_id: String,
+}
+
+/// Something that happened during an export, reported to the `ExportSink` passed to
+/// `start_export` (the app forwards each one as a Tauri event named by `name()`)
+#[derive(Debug, Clone)]
+pub enum ExportEvent {
+    Progress(ExportProgress),
+    Completed(ExportCompleted),
+    Failed(ExportFailed),
+    Cancelled(ExportCancelled),
+}
+
+impl ExportEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportEvent::Progress(_) => "export_progress",
+            ExportEvent::Completed(_) => "export_complete",
+            ExportEvent::Failed(_) => "export_failed",
+            ExportEvent::Cancelled(_) => "export_cancelled",
+        }
+    }
+}
+
+/// Receives the events of an export: the app forwards them to the frontend, the headless
+/// renderer prints them and tests record them
+pub trait ExportSink: Send + Sync + 'static {
+    fn send(&self, event: ExportEvent);
+}
+
+impl<F> ExportSink for F
+where
+    F: Fn(ExportEvent) + Send + Sync + 'static,
+{
+    fn send(&self, event: ExportEvent) {
+        self(event)
+    }
+}
+
+/// An export ready to render
+pub struct ExportJob {
+    pub ffmpeg_path: PathBuf,
+    pub plan: ExportPlan,
+    pub output_path: String,
+    /// FFmpeg's log output is written to `<log_dir>/<id>.log` when set
+    pub log_dir: Option<PathBuf>,
+}
+
+/// How a background export ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportOutcome {
+    Completed,
+    Failed(ExportFailure),
+    Cancelled,
+}
+
+/// A registered export; `child` is `None` while the export is reserved but FFmpeg has
+/// not been spawned yet
+struct RunningExport {
+    child: Option<Arc<Mutex<Child>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// An export ID claimed in the registry before its FFmpeg process exists, so the ID
+/// can't be taken twice and the export can be cancelled while it is being prepared
+/// Dropping a reservation that was never started frees the ID
+pub struct ExportReservation {
+    jobs: ExportJobs,
+    id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ExportReservation {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for ExportReservation {
+    fn drop(&mut self) {
+        let mut running = self.jobs.running.lock().unwrap();
+        if running
+            .get(&self.id)
+            .is_some_and(|job| job.child.is_none() && Arc::ptr_eq(&job.cancelled, &self.cancelled))
+        {
+            running.remove(&self.id);
+        }
+    }
+}
+
+/// Registry of running exports, managed as Tauri state
+/// Clones share the registry, so monitor threads can hold one
+#[derive(Default, Clone)]
+pub struct ExportJobs {
+    running: Arc<Mutex<HashMap<String, RunningExport>>>,
+}
+
+impl ExportJobs {
+    /// Claims an export ID, failing if an export with that ID is already reserved or running
+    pub fn reserve(&self, job_id: String) -> Result<ExportReservation, ClipforgeError> {
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(&job_id) {
+            return Err(ClipforgeError::new(
+                ErrorKind::InvalidInput,
+                format!("An export with ID {} is already running", job_id),
+            ));
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        running.insert(job_id.clone(), RunningExport { child: None, cancelled: cancelled.clone() });
+        Ok(ExportReservation { jobs: self.clone(), id: job_id, cancelled })
+    }
+
+    /// Kills the FFmpeg process of a running export
+    /// The monitor thread removes the partial output and reports `ExportEvent::Cancelled`;
+    /// an export whose FFmpeg has already exited is left to finish normally, and one that
+    /// is still being prepared is reported as cancelled instead of starting
+    pub fn cancel(&self, job_id: &str) -> Result<(), ClipforgeError> {
+        let running = self.running.lock().unwrap();
+        let job = running.get(job_id).ok_or_else(|| {
+            ClipforgeError::new(ErrorKind::InvalidInput, format!("No running export with ID: {}", job_id))
+        })?;
+        let Some(child) = &job.child else {
+            job.cancelled.store(true, Ordering::SeqCst);
+            return Ok(());
+        };
+
+        // Holding the child's lock keeps the monitor from reaping it until the flag is set
+        let mut child = child.lock().unwrap();
+        if let Ok(Some(_)) = child.try_wait() {
+            return Ok(());
+        }
+        child
+            .kill()
+            .map_err(|e| ClipforgeError::new(ErrorKind::FfmpegFailed, format!("Failed to stop FFmpeg: {}", e)))?;
+        job.cancelled.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Generates a unique ID for a new export job
+pub fn new_job_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("export-{}-{}", millis, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+/// Spawns FFmpeg for a reserved export job and monitors it on background threads
+/// Returns as soon as the process has started; progress and the outcome are sent to
+/// `sink`, and the outcome is passed to `on_finish` once the process has exited
+/// An export cancelled before this is called is reported as cancelled without starting
+pub fn start_export(
+    reservation: ExportReservation,
+    job: ExportJob,
+    sink: impl ExportSink,
+    on_finish: impl FnOnce(ExportOutcome) + Send + 'static,
+) -> Result<(), ClipforgeError> {
+    let ExportJob {
+        ffmpeg_path,
+        plan,
+        output_path,
+        log_dir,
+    } = job;
+    let job_id = reservation.id.clone();
+    if reservation.cancelled.load(Ordering::SeqCst) {
+        log::info!("Export {} cancelled before it started", job_id);
+        sink.send(ExportEvent::Cancelled(ExportCancelled { job_id }));
+        on_finish(ExportOutcome::Cancelled);
+        return Ok(());
+    }
+    let sink = Arc::new(sink);
+    let command_line = format_command_line(&ffmpeg_path, &plan.args);
+
+    // Open the per-export log; exports still run if it can't be created
+    let log_path = log_dir.map(|dir| dir.join(format!("{}.log", job_id)));
+    let mut log_file = log_path.as_ref().and_then(|path| {
+        fs::create_dir_all(path.parent()?).ok()?;
+        fs::File::create(path).ok().map(BufWriter::new)
+    });
+    if let Some(log) = log_file.as_mut() {
+        let _ = writeln!(log, "Command: {}\n", command_line);
+    }
+    let log_path = log_path.map(|path| path.to_string_lossy().to_string());
+
+    // Spawn FFmpeg process with piped stderr for progress tracking
+    let mut child = Command::new(&ffmpeg_path)
+        .args(&plan.args)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            ClipforgeError::new(ErrorKind::FfmpegFailed, format!("Failed to spawn FFmpeg: {}", e)).with_path(&ffmpeg_path)
+        })?;
+
+    let started_at = Instant::now();
+
+    // Report initial progress
+    sink.send(ExportEvent::Progress(ExportProgress::starting(job_id.clone())));
+
+    // Stream stderr for progress updates and diagnostics; the thread returns the last
+    // progress it saw, the recent log lines and the log file
+    let progress_thread = child.stderr.take().map(|stderr| {
+        let sink = sink.clone();
+        let job_id = job_id.clone();
+        let expected_duration = plan.expected_duration;
+
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            let mut parser = ProgressParser::new();
+            let mut last_progress = ExportProgress::starting(job_id.clone());
+            let mut stderr_tail = StderrTail::default();
+            let mut last_emit = Instant::now();
+            for line in reader.lines().map_while(Result::ok) {
+                if !ProgressParser::is_progress_line(&line) {
+                    stderr_tail.push(&line);
+                    if let Some(log) = log_file.as_mut() {
+                        let _ = writeln!(log, "{}", line);
+                    }
+                }
+                let Some(block) = parser.feed(&line) else {
+                    continue;
+                };
+                last_progress = block.to_progress(&job_id, expected_duration, started_at.elapsed());
+
+                // Report progress every 300ms, and always for the final block
+                if block.end || last_emit.elapsed().as_millis() >= 300 {
+                    sink.send(ExportEvent::Progress(last_progress.clone()));
+                    last_emit = Instant::now();
+                }
+            }
+            (last_progress, stderr_tail.into_lines(), log_file)
+        })
+    });
+
+    // Attach the process to the reservation; a cancel that arrived while it was spawning
+    // kills it straight away and the monitor reports the cancellation
+    let child = Arc::new(Mutex::new(child));
+    let cancelled = reservation.cancelled.clone();
+    let jobs = reservation.jobs.clone();
+    if let Some(running) = jobs.running.lock().unwrap().get_mut(&job_id) {
+        running.child = Some(child.clone());
+        if cancelled.load(Ordering::SeqCst) {
+            let _ = child.lock().unwrap().kill();
+        }
+    }
+    drop(reservation);
+
+    std::thread::spawn(move || {
+        // Poll instead of blocking in wait() so cancel_export can take the lock and kill the child
+        let status = loop {
+            match child.lock().unwrap().try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {}
+                Err(e) => break Err(format!("Failed to wait for FFmpeg: {}", e)),
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+
+        jobs.running.lock().unwrap().remove(&job_id);
+        let (last_progress, stderr_tail, mut log_file) = progress_thread
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_else(|| (ExportProgress::starting(job_id.clone()), Vec::new(), None));
+
+        // A run that finished cleanly counts as completed even if a cancel raced with its
+        // exit; only a process that was killed is reported as cancelled
+        let outcome = match &status {
+            Ok(status) if status.success() => ExportOutcome::Completed,
+            _ if cancelled.load(Ordering::SeqCst) => ExportOutcome::Cancelled,
+            Ok(status) => ExportOutcome::Failed(ExportFailure::from_exit(
+                status.code(),
+                command_line,
+                stderr_tail,
+                log_path,
+            )),
+            Err(error) => ExportOutcome::Failed(ExportFailure::from_error(
+                error.clone(),
+                command_line,
+                log_path,
+            )),
+        };
+
+        if let Some(log) = log_file.as_mut() {
+            let _ = writeln!(log, "\nResult: {:?}", outcome);
+            let _ = log.flush();
+        }
+
+        match &outcome {
+            ExportOutcome::Completed => {
+                // Report 100% completion with the final file size
+                let output_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+                sink.send(ExportEvent::Progress(last_progress.complete(started_at.elapsed(), output_size)));
+                log::info!("Export {} completed successfully", job_id);
+                sink.send(ExportEvent::Completed(ExportCompleted { job_id, output_path }));
+            }
+            ExportOutcome::Failed(failure) => {
+                log::error!("Export {} failed: {}", job_id, failure.message);
+                sink.send(ExportEvent::Failed(ExportFailed { job_id, failure: failure.clone() }));
+            }
+            ExportOutcome::Cancelled => {
+                // Remove the partial output file left behind by the killed process
+                let _ = fs::remove_file(&output_path);
+                log::info!("Export {} cancelled", job_id);
+                sink.send(ExportEvent::Cancelled(ExportCancelled { job_id }));
+            }
+        }
+        on_finish(outcome);
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportRequirements;
+
+    #[test]
+    fn reserved_export_can_be_cancelled_before_it_starts() {
+        let jobs = ExportJobs::default();
+        let reservation = jobs.reserve("job-1".to_string()).unwrap();
+        assert!(jobs.reserve("job-1".to_string()).is_err());
+
+        jobs.cancel("job-1").unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let job = ExportJob {
+            ffmpeg_path: PathBuf::from("ffmpeg-that-must-not-run"),
+            plan: ExportPlan {
+                args: Vec::new(),
+                filter_complex: String::new(),
+                expected_duration: 1.0,
+                requirements: ExportRequirements {
+                    video_encoder: String::new(),
+                    audio_encoder: String::new(),
+                    muxer: String::new(),
+                    filters: Default::default(),
+                },
+            },
+            output_path: "out.mp4".to_string(),
+            log_dir: None,
+        };
+        let sink = move |event: ExportEvent| recorded.lock().unwrap().push(event.name());
+        start_export(reservation, job, sink, move |outcome| {
+            let _ = sender.send(outcome);
+        })
+        .unwrap();
+
+        assert_eq!(receiver.recv().unwrap(), ExportOutcome::Cancelled);
+        assert_eq!(*events.lock().unwrap(), ["export_cancelled"]);
+        // The ID is free again once the reservation is gone
+        drop(jobs.reserve("job-1".to_string()).unwrap());
+    }
+}
@@ -0,0 +1,18 @@
+//! Export pipeline: turns an `ExportRequest` into an FFmpeg invocation
+
+pub mod diagnostics;
+pub mod filter_graph;
+pub mod jobs;
+pub mod plan;
+pub mod prepare;
+pub mod progress;
+pub mod queue;
+
+pub use diagnostics::ExportFailure;
+pub use jobs::{
+    new_job_id, start_export, ExportEvent, ExportJob, ExportJobs, ExportOutcome, ExportReservation, ExportSink,
+};
+pub use plan::{build_export_plan, ExportPlan, ExportRequirements, ExportSource};
+pub use prepare::prepare_export;
+pub use progress::{ExportProgress, ExportStage};
+pub use queue::{QueueJobStatus, QueuedExport, RenderQueue};
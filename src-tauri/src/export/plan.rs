@@ -0,0 +1,1206 @@
+//! Builds the FFmpeg command line for an export from an `ExportRequest`
+//!
+//! This is pure: it takes the request plus what is known about each source
+//! file and returns the arguments, without touching the filesystem or
+//! spawning processes.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::filter_graph::{Chain, Filter, FilterGraph, Pad};
+use crate::{ExportRequest, MediaKind, OverlayAnchor, OverlayTransform, TimelineClip};
+
+/// Frame rate of the exported video when no Track 0 source reports one
+const DEFAULT_FPS: f64 = 30.0;
+/// Sample rate of the exported audio
+const AUDIO_SAMPLE_RATE: u32 = 48000;
+/// Gaps and overlaps shorter than this are treated as rounding noise
+const TIME_EPSILON: f64 = 0.001;
+
+/// What export needs to know about a source file
+#[derive(Debug, Clone)]
+pub struct ExportSource {
+    pub path: String,
+    /// Images are looped into video; audio-only files are mixed into the soundtrack
+    pub kind: MediaKind,
+    /// Number of audio streams; 0 means the clip gets generated silence
+    pub audio_streams: usize,
+    /// Audio stream used by clips that don't pick one
+    pub default_audio_stream: usize,
+    /// Displayed size (rotation applied), 0 when unknown
+    pub width: u32,
+    pub height: u32,
+    /// Average frame rate, 0 when unknown
+    pub frame_rate: f64,
+}
+
+/// A fully built FFmpeg invocation for an export
+#[derive(Debug, Clone)]
+pub struct ExportPlan {
+    /// Complete FFmpeg arguments, including `-filter_complex` and the output path
+    pub args: Vec<String>,
+    /// The rendered filter graph (also present in `args`)
+    pub filter_complex: String,
+    /// Length of the timeline in seconds, used for progress tracking
+    pub expected_duration: f64,
+    /// Encoders, muxer and filters the FFmpeg build must provide
+    pub requirements: ExportRequirements,
+}
+
+/// What an export needs from the FFmpeg build
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRequirements {
+    pub video_encoder: String,
+    pub audio_encoder: String,
+    pub muxer: String,
+    pub filters: BTreeSet<String>,
+}
+
+/// Maps an export resolution preset to output dimensions
+pub fn target_resolution(resolution: &str) -> (u32, u32) {
+    match resolution {
+        "720p" => (1280, 720),
+        "1080p" => (1920, 1080),
+        "1440p" => (2560, 1440),
+        "4K" => (3840, 2160),
+        _ => (1920, 1080), // Default to 1080p
+    }
+}
+
+/// Builds the FFmpeg arguments for an export
+/// `sources` maps each `TimelineClip::clip_id` to its source file
+pub fn build_export_plan(
+    request: &ExportRequest,
+    sources: &HashMap<String, ExportSource>,
+) -> Result<ExportPlan, String> {
+    if request.clips.is_empty() {
+        return Err("No clips to export".to_string());
+    }
+
+    // Group clips by track (ascending, so higher tracks stack over lower ones) and sort by start time
+    let mut tracks: BTreeMap<u32, Vec<&TimelineClip>> = BTreeMap::new();
+    let mut audio_clips: Vec<&TimelineClip> = Vec::new();
+    for clip in &request.clips {
+        if !clip.start_time.is_finite() || clip.start_time < 0.0 {
+            return Err(format!(
+                "Clip {} on track {} has an invalid start time: {}",
+                clip.id, clip.track, clip.start_time
+            ));
+        }
+        let clip_duration = clip.trim_end - clip.trim_start;
+        if clip_duration.is_nan() || clip_duration <= 0.0 {
+            return Err(format!(
+                "Clip {} on track {} has an empty trim range ({} - {})",
+                clip.id, clip.track, clip.trim_start, clip.trim_end
+            ));
+        }
+
+        // Audio-only clips have no picture, so they're mixed into the soundtrack whatever their track
+        if sources.get(&clip.clip_id).is_some_and(|source| source.kind == MediaKind::Audio) {
+            audio_clips.push(clip);
+        } else {
+            tracks.entry(clip.track).or_default().push(clip);
+        }
+    }
+    for clips in tracks.values_mut() {
+        clips.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    }
+    audio_clips.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    let track0_clips = tracks.remove(&0).unwrap_or_default();
+    if track0_clips.is_empty() {
+        return Err("No clips on main track (Track 0) to export".to_string());
+    }
+
+    // Track 0 is concatenated, so its clips can't overlap
+    for pair in track0_clips.windows(2) {
+        let previous_end = clip_end(pair[0]);
+        if pair[1].start_time < previous_end - TIME_EPSILON {
+            return Err(format!(
+                "Cannot render track 0: clips {} and {} overlap by {:.3}s",
+                pair[0].id, pair[1].id, previous_end - pair[1].start_time
+            ));
+        }
+    }
+
+    // The timeline ends where the last clip on any track (or audio clip) ends, including any gaps
+    let expected_duration = request.clips.iter().map(clip_end).fold(0.0, f64::max);
+
+    // Output size and frame rate follow the Track 0 sources: "Source" keeps the first clip's
+    // displayed size, and the frame rate is the highest of their rates
+    let track0_sources = track0_clips
+        .iter()
+        .map(|clip| lookup_source(sources, clip))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (target_width, target_height) = match request.resolution.as_str() {
+        "Source" => track0_sources
+            .first()
+            .and_then(|source| source_size(source))
+            .unwrap_or_else(|| target_resolution(&request.resolution)),
+        preset => target_resolution(preset),
+    };
+    let frame_rate = frame_rate_arg(
+        track0_sources
+            .iter()
+            .map(|source| source.frame_rate)
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .fold(None, |max: Option<f64>, rate| Some(max.map_or(rate, |max| max.max(rate))))
+            .unwrap_or(DEFAULT_FPS),
+    );
+
+    let mut args: Vec<String> = vec![
+        "-y".to_string(),
+        "-progress".to_string(),
+        "pipe:2".to_string(),
+    ];
+    let mut graph = FilterGraph::new();
+    let mut input_index = 0;
+
+    // Track 0 is rendered as an ordered list of segments: clips and the gaps
+    // between them. Each segment contributes one video and one audio pad.
+    let mut segments: Vec<(Pad, Pad)> = Vec::new();
+    let mut timeline_cursor = 0.0;
+
+    for (idx, clip) in track0_clips.iter().enumerate() {
+        let source = lookup_source(sources, clip)?;
+
+        // Fill any gap before this clip with black video and silent audio
+        let gap = clip.start_time - timeline_cursor;
+        if gap > TIME_EPSILON {
+            segments.push(push_gap_segment(
+                &mut graph,
+                &idx.to_string(),
+                gap,
+                (target_width, target_height),
+                &frame_rate,
+            ));
+        }
+
+        let clip_duration = clip.trim_end - clip.trim_start;
+        push_trimmed_input(&mut args, clip, source);
+
+        // Scale to target resolution, letterboxing to preserve aspect ratio
+        let video_pad = Pad::label(format!("v{}", idx));
+        graph.push(
+            Chain::new()
+                .input(Pad::video(input_index))
+                .filter(
+                    Filter::new("scale")
+                        .arg(target_width)
+                        .arg(target_height)
+                        .opt("force_original_aspect_ratio", "decrease"),
+                )
+                .filter(
+                    Filter::new("pad")
+                        .arg(target_width)
+                        .arg(target_height)
+                        .arg("(ow-iw)/2")
+                        .arg("(oh-ih)/2"),
+                )
+                .filter(Filter::new("setsar").arg(1))
+                .filter(Filter::new("fps").arg(&frame_rate))
+                .filter(Filter::new("setpts").arg("PTS-STARTPTS"))
+                // Hold the last frame and cut to the clip length, like the audio below, so a
+                // source that ends early or runs long doesn't shift the clips after it
+                .filter(Filter::new("tpad").opt("stop_mode", "clone").opt("stop_duration", clip_duration))
+                .filter(Filter::new("trim").opt("duration", clip_duration))
+                .output(video_pad.clone()),
+        );
+
+        // Normalize audio format and pad/trim to the clip length so each segment
+        // stays in sync. Clips without audio get generated silence.
+        let audio_pad = Pad::label(format!("a{}", idx));
+        let audio_chain = if let Some(stream) = audio_stream(clip, source)? {
+            normalized_audio(audio_input(input_index, stream)).filter(Filter::new("apad"))
+        } else {
+            Chain::new().filter(silence_source())
+        };
+        graph.push(
+            audio_chain
+                .filter(Filter::new("atrim").opt("duration", clip_duration))
+                .filter(Filter::new("asetpts").arg("PTS-STARTPTS"))
+                .output(audio_pad.clone()),
+        );
+
+        segments.push((video_pad, audio_pad));
+        timeline_cursor = clip.start_time + clip_duration;
+        input_index += 1;
+    }
+
+    // Extend the base with black/silence if clips on higher tracks or audio clips run past the end of Track 0
+    let trailing_gap = expected_duration - timeline_cursor;
+    if trailing_gap > TIME_EPSILON {
+        segments.push(push_gap_segment(
+            &mut graph,
+            "end",
+            trailing_gap,
+            (target_width, target_height),
+            &frame_rate,
+        ));
+    }
+
+    // Audio clips and overlays with sound are mixed over the Track 0 audio
+    let overlays_have_audio = tracks
+        .values()
+        .flatten()
+        .any(|clip| sources.get(&clip.clip_id).is_some_and(|source| source.audio_streams > 0));
+    let needs_mix = overlays_have_audio || !audio_clips.is_empty();
+
+    // Concatenate all Track 0 segments (video and audio pairs)
+    let base_video = Pad::label("vbase");
+    let base_audio = Pad::label(if needs_mix { "abase" } else { "outa" });
+    let mut concat = Chain::new();
+    for (video, audio) in &segments {
+        concat = concat.input(video.clone()).input(audio.clone());
+    }
+    graph.push(
+        concat
+            .filter(Filter::new("concat").opt("n", segments.len()).opt("v", 1).opt("a", 1))
+            .output(base_video.clone())
+            .output(base_audio.clone()),
+    );
+
+    // Overlay every clip on Track 1 and above during its timeline window, lowest track first.
+    // Each overlay is delayed to its start_time and chained onto the previous result,
+    // so overlapping and sequential overlays both composite correctly.
+    let mut video_out = base_video;
+    let mut mix_inputs: Vec<Pad> = Vec::new();
+    for (idx, overlay_clip) in tracks.values().flatten().enumerate() {
+        let source = lookup_source(sources, overlay_clip)
+            .map_err(|e| format!("Cannot render track {}: {}", overlay_clip.track, e))?;
+        push_trimmed_input(&mut args, overlay_clip, source);
+
+        // Size and style the overlay from its transform, then shift its timestamps to the clip's start_time
+        let transform = overlay_clip.transform.clone().unwrap_or_default();
+        let (layout_filters, (x, y)) = overlay_layout(&transform, target_width, target_height)
+            .map_err(|e| format!("Cannot render track {}: {}", overlay_clip.track, e))?;
+        let overlay_pad = Pad::label(format!("ov{}", idx));
+        graph.push(
+            Chain::new()
+                .input(Pad::video(input_index))
+                .filters(layout_filters)
+                .filter(Filter::new("setpts").arg(format!("PTS-STARTPTS+{}/TB", overlay_clip.start_time)))
+                .output(overlay_pad.clone()),
+        );
+
+        // Composite at the transform position, only while the clip is on the timeline
+        let next_pad = Pad::label(format!("vov{}", idx));
+        graph.push(
+            Chain::new()
+                .input(video_out)
+                .input(overlay_pad)
+                .filter(
+                    Filter::new("overlay")
+                        .arg(x)
+                        .arg(y)
+                        .opt("eof_action", "pass")
+                        .opt(
+                            "enable",
+                            format!("'between(t,{},{})'", overlay_clip.start_time, clip_end(overlay_clip)),
+                        ),
+                )
+                .output(next_pad.clone()),
+        );
+        video_out = next_pad;
+
+        // The overlay's sound plays during its window, delayed like an audio clip
+        if let Some(stream) = audio_stream(overlay_clip, source)? {
+            let delay_ms = (overlay_clip.start_time * 1000.0).round() as u64;
+            let audio_pad = Pad::label(format!("ova{}", idx));
+            graph.push(
+                normalized_audio(audio_input(input_index, stream))
+                    .filter(Filter::new("asetpts").arg("PTS-STARTPTS"))
+                    .filter(Filter::new("adelay").opt("delays", delay_ms).opt("all", 1))
+                    .output(audio_pad.clone()),
+            );
+            mix_inputs.push(audio_pad);
+        }
+        input_index += 1;
+    }
+
+    // Mix overlay sound and audio-only clips (music beds, voice-over) over the Track 0 audio
+    // at their timeline positions
+    let audio_out = if !needs_mix {
+        base_audio
+    } else {
+        for (idx, audio_clip) in audio_clips.iter().enumerate() {
+            let source = lookup_source(sources, audio_clip)?;
+            let stream = audio_stream(audio_clip, source)?
+                .ok_or_else(|| format!("Audio clip {} has no audio stream", audio_clip.id))?;
+            push_trimmed_input(&mut args, audio_clip, source);
+
+            let delay_ms = (audio_clip.start_time * 1000.0).round() as u64;
+            let mix_pad = Pad::label(format!("mix{}", idx));
+            graph.push(
+                normalized_audio(audio_input(input_index, stream))
+                    .filter(Filter::new("asetpts").arg("PTS-STARTPTS"))
+                    .filter(Filter::new("adelay").opt("delays", delay_ms).opt("all", 1))
+                    .output(mix_pad.clone()),
+            );
+            mix_inputs.push(mix_pad);
+            input_index += 1;
+        }
+
+        // The base already spans the whole timeline; normalize=0 keeps every input at full volume
+        let mixed = Pad::label("outa");
+        let inputs = mix_inputs.len() + 1;
+        let mix = mix_inputs.into_iter().fold(Chain::new().input(base_audio), Chain::input);
+        graph.push(
+            mix.filter(
+                Filter::new("amix")
+                    .opt("inputs", inputs)
+                    .opt("duration", "first")
+                    .opt("dropout_transition", 0)
+                    .opt("normalize", 0),
+            )
+            .output(mixed.clone()),
+        );
+        mixed
+    };
+
+    let filter_complex = graph
+        .render(&[video_out.clone(), audio_out.clone()])
+        .map_err(|e| format!("Invalid export filter graph: {}", e))?;
+
+    args.push("-filter_complex".to_string());
+    args.push(filter_complex.clone());
+
+    // Map the output video (the last overlay result, or the concatenated base) and the Track 0 audio
+    args.push("-map".to_string());
+    args.push(video_out.to_string());
+    args.push("-map".to_string());
+    args.push(audio_out.to_string());
+
+    // Encoders default to the format's usual pair; the request may pick others (e.g. a fallback)
+    let (default_video, default_audio) = default_encoders(&request.format);
+    let video_encoder = request.video_codec.clone().unwrap_or_else(|| default_video.to_string());
+    let audio_encoder = request.audio_codec.clone().unwrap_or_else(|| default_audio.to_string());
+    args.extend(codec_args(&video_encoder, &audio_encoder));
+    args.push(request.output_path.clone());
+
+    let requirements = ExportRequirements {
+        video_encoder,
+        audio_encoder,
+        muxer: output_muxer(&request.format).to_string(),
+        filters: graph.filter_names(),
+    };
+
+    Ok(ExportPlan {
+        args,
+        filter_complex,
+        expected_duration,
+        requirements,
+    })
+}
+
+/// Video and audio encoders used for an export format unless the request overrides them
+pub fn default_encoders(format: &str) -> (&'static str, &'static str) {
+    match format {
+        "webm" => ("libvpx-vp9", "libopus"),
+        // "mov" and "mp4" both use H.264/AAC
+        _ => ("libx264", "aac"),
+    }
+}
+
+/// FFmpeg muxer that writes an export format
+pub fn output_muxer(format: &str) -> &'static str {
+    match format {
+        "webm" => "webm",
+        "mov" => "mov",
+        _ => "mp4",
+    }
+}
+
+/// Output codec settings for a video and audio encoder pair
+pub fn codec_args(video_encoder: &str, audio_encoder: &str) -> Vec<String> {
+    let video: &[&str] = match video_encoder {
+        "libx264" => &["-preset", "medium", "-crf", "23"],
+        "libvpx-vp9" => &["-crf", "30", "-b:v", "0"],
+        // VP8 treats -crf as a quality floor under the -b:v cap
+        "libvpx" => &["-crf", "10", "-b:v", "8M"],
+        "libsvtav1" => &["-preset", "8", "-crf", "35"],
+        // Hardware and fallback encoders are driven by bitrate
+        _ => &["-b:v", "8M"],
+    };
+    let audio: &[&str] = match audio_encoder {
+        "libopus" | "libvorbis" => &["-b:a", "128k"],
+        // FFmpeg's native Opus encoder is still marked experimental
+        "opus" => &["-strict", "-2", "-b:a", "128k"],
+        _ => &["-b:a", "192k"],
+    };
+
+    let mut args = vec!["-c:v".to_string(), video_encoder.to_string()];
+    args.extend(video.iter().map(|arg| arg.to_string()));
+    args.push("-c:a".to_string());
+    args.push(audio_encoder.to_string());
+    args.extend(audio.iter().map(|arg| arg.to_string()));
+    args
+}
+
+/// The source audio stream a clip plays, or None if the source has no audio
+fn audio_stream(clip: &TimelineClip, source: &ExportSource) -> Result<Option<usize>, String> {
+    match clip.audio_stream {
+        Some(stream) if stream >= source.audio_streams => Err(format!(
+            "Clip {} uses audio stream {} but {} has {} audio stream(s)",
+            clip.id, stream, source.path, source.audio_streams
+        )),
+        Some(stream) => Ok(Some(stream)),
+        None if source.audio_streams == 0 => Ok(None),
+        None => Ok(Some(source.default_audio_stream.min(source.audio_streams - 1))),
+    }
+}
+
+/// Even output dimensions for a source's displayed size, if known
+fn source_size(source: &ExportSource) -> Option<(u32, u32)> {
+    if source.width == 0 || source.height == 0 {
+        return None;
+    }
+    // H.264 with yuv420p needs even dimensions
+    Some(((source.width / 2 * 2).max(2), (source.height / 2 * 2).max(2)))
+}
+
+/// Formats a frame rate for FFmpeg, keeping NTSC rates exact (29.97 -> 30000/1001)
+fn frame_rate_arg(rate: f64) -> String {
+    let ntsc_base = (rate * 1.001).round();
+    if (rate - ntsc_base).abs() > 0.005 && (rate * 1.001 - ntsc_base).abs() < 0.005 {
+        format!("{}/1001", ntsc_base as u64 * 1000)
+    } else {
+        format!("{}", (rate * 1000.0).round() / 1000.0)
+    }
+}
+
+/// Where a clip ends on the timeline
+fn clip_end(clip: &TimelineClip) -> f64 {
+    clip.start_time + (clip.trim_end - clip.trim_start)
+}
+
+fn lookup_source<'a>(
+    sources: &'a HashMap<String, ExportSource>,
+    clip: &TimelineClip,
+) -> Result<&'a ExportSource, String> {
+    sources
+        .get(&clip.clip_id)
+        .ok_or_else(|| format!("Source clip not found: {}", clip.clip_id))
+}
+
+/// Adds an input with seek and duration for faster processing
+/// Still images are looped for the clip's duration instead
+fn push_trimmed_input(args: &mut Vec<String>, clip: &TimelineClip, source: &ExportSource) {
+    if source.kind == MediaKind::Image {
+        args.push("-loop".to_string());
+        args.push("1".to_string());
+    } else {
+        args.push("-ss".to_string());
+        args.push(clip.trim_start.to_string());
+    }
+    args.push("-t".to_string());
+    args.push((clip.trim_end - clip.trim_start).to_string());
+    args.push("-i".to_string());
+    args.push(source.path.clone());
+}
+
+/// Pad for one of an input's audio streams; `[i:a]` already means the first
+fn audio_input(input_index: usize, stream: usize) -> Pad {
+    match stream {
+        0 => Pad::audio(input_index),
+        stream => Pad::audio_stream(input_index, stream),
+    }
+}
+
+/// Converts an audio input to the export sample rate and stereo float samples
+fn normalized_audio(input: Pad) -> Chain {
+    Chain::new()
+        .input(input)
+        .filter(Filter::new("aresample").arg(AUDIO_SAMPLE_RATE))
+        .filter(
+            Filter::new("aformat")
+                .opt("sample_fmts", "fltp")
+                .opt("channel_layouts", "stereo"),
+        )
+}
+
+/// Generated stereo silence at the export sample rate
+fn silence_source() -> Filter {
+    Filter::new("anullsrc")
+        .opt("channel_layout", "stereo")
+        .opt("sample_rate", AUDIO_SAMPLE_RATE)
+}
+
+/// Adds a black video + silent audio segment of the given duration and returns its pads
+fn push_gap_segment(
+    graph: &mut FilterGraph,
+    label: &str,
+    duration: f64,
+    (target_width, target_height): (u32, u32),
+    frame_rate: &str,
+) -> (Pad, Pad) {
+    let video_pad = Pad::label(format!("gv{}", label));
+    let audio_pad = Pad::label(format!("ga{}", label));
+    graph.push(
+        Chain::new()
+            .filter(
+                Filter::new("color")
+                    .opt("c", "black")
+                    .opt("s", format!("{}x{}", target_width, target_height))
+                    .opt("r", frame_rate)
+                    .opt("d", duration),
+            )
+            .filter(Filter::new("setsar").arg(1))
+            .output(video_pad.clone()),
+    );
+    graph.push(
+        Chain::new()
+            .filter(silence_source())
+            .filter(Filter::new("atrim").opt("duration", duration))
+            .filter(Filter::new("asetpts").arg("PTS-STARTPTS"))
+            .output(audio_pad.clone()),
+    );
+    (video_pad, audio_pad)
+}
+
+/// Builds the filters that size and style an overlay, plus its overlay x/y expressions
+fn overlay_layout(
+    transform: &OverlayTransform,
+    target_width: u32,
+    target_height: u32,
+) -> Result<(Vec<Filter>, (String, String)), String> {
+    if !(transform.width > 0.0 && transform.width <= 1.0) {
+        return Err(format!("Overlay width must be between 0 and 1, got {}", transform.width));
+    }
+    if !(0.0..=1.0).contains(&transform.opacity) {
+        return Err(format!("Overlay opacity must be between 0 and 1, got {}", transform.opacity));
+    }
+    if !(0.0..=0.5).contains(&transform.corner_radius) {
+        return Err(format!("Overlay corner radius must be between 0 and 0.5, got {}", transform.corner_radius));
+    }
+
+    // Scale to the requested fraction of the frame, keeping the source aspect ratio (even sizes for yuv420p)
+    let overlay_width = (((transform.width * target_width as f64) / 2.0).round() as u32 * 2).max(2);
+    let mut filters = vec![
+        Filter::new("scale").arg(overlay_width).arg(-2),
+        Filter::new("setsar").arg(1),
+    ];
+
+    if let Some(border) = &transform.border {
+        if !border.color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#') {
+            return Err(format!("Invalid overlay border color: {}", border.color));
+        }
+        let border_px = (border.width * target_width as f64).round() as u32;
+        if border_px > 0 {
+            filters.push(
+                Filter::new("pad")
+                    .arg(format!("iw+{}", border_px * 2))
+                    .arg(format!("ih+{}", border_px * 2))
+                    .arg(border_px)
+                    .arg(border_px)
+                    .opt("color", &border.color),
+            );
+        }
+    }
+
+    // Opacity and rounded corners both need an alpha channel
+    if transform.opacity < 1.0 || transform.corner_radius > 0.0 {
+        filters.push(Filter::new("format").arg("yuva420p"));
+    }
+    if transform.opacity < 1.0 {
+        filters.push(Filter::new("colorchannelmixer").opt("aa", transform.opacity));
+    }
+    if transform.corner_radius > 0.0 {
+        // Clear alpha outside a quarter circle of radius R in each corner
+        let radius = format!("({}*min(W,H))", transform.corner_radius);
+        filters.push(
+            Filter::new("geq")
+                .opt("lum", "'p(X,Y)'")
+                .opt("cb", "'cb(X,Y)'")
+                .opt("cr", "'cr(X,Y)'")
+                .opt(
+                    "a",
+                    format!(
+                        "'alpha(X,Y)*lte(hypot(max(0,max({0}-X,X-W+{0})),max(0,max({0}-Y,Y-H+{0}))),{0})'",
+                        radius
+                    ),
+                ),
+        );
+    }
+
+    // Offsets are measured inward from the anchor edge(s)
+    let offset_x = (transform.offset_x * target_width as f64).round() as i64;
+    let offset_y = (transform.offset_y * target_height as f64).round() as i64;
+    let position = match transform.anchor {
+        OverlayAnchor::TopLeft => (offset_x.to_string(), offset_y.to_string()),
+        OverlayAnchor::TopRight => (format!("W-w-{}", offset_x), offset_y.to_string()),
+        OverlayAnchor::BottomLeft => (offset_x.to_string(), format!("H-h-{}", offset_y)),
+        OverlayAnchor::BottomRight => (format!("W-w-{}", offset_x), format!("H-h-{}", offset_y)),
+        OverlayAnchor::Center => (format!("(W-w)/2+{}", offset_x), format!("(H-h)/2+{}", offset_y)),
+    };
+
+    Ok((filters, position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::clip;
+    use crate::OverlayBorder;
+
+    fn request(clips: Vec<TimelineClip>) -> ExportRequest {
+        ExportRequest {
+            clips,
+            output_path: "/tmp/out.mp4".to_string(),
+            resolution: "1080p".to_string(),
+            format: "mp4".to_string(),
+            video_codec: None,
+            audio_codec: None,
+        }
+    }
+
+    fn sources() -> HashMap<String, ExportSource> {
+        let mut sources: HashMap<String, ExportSource> =
+            [("a.mp4", true), ("b.mp4", true), ("silent.mp4", false), ("cam.mp4", true)]
+                .into_iter()
+                .map(|(path, has_audio)| {
+                    let source = ExportSource {
+                        path: path.to_string(),
+                        kind: MediaKind::Video,
+                        audio_streams: has_audio as usize,
+                        default_audio_stream: 0,
+                        width: 1920,
+                        height: 1080,
+                        frame_rate: 30.0,
+                    };
+                    (path.to_string(), source)
+                })
+                .collect();
+        let still_and_music = [
+            ("logo.png", MediaKind::Image, 0, 512),
+            ("music.mp3", MediaKind::Audio, 1, 0),
+        ];
+        for (path, kind, audio_streams, size) in still_and_music {
+            let source = ExportSource {
+                path: path.to_string(),
+                kind,
+                audio_streams,
+                default_audio_stream: 0,
+                width: size,
+                height: size,
+                frame_rate: 0.0,
+            };
+            sources.insert(path.to_string(), source);
+        }
+        sources
+    }
+
+    fn inputs(plan: &ExportPlan) -> Vec<&str> {
+        plan.args
+            .windows(2)
+            .filter(|pair| pair[0] == "-i")
+            .map(|pair| pair[1].as_str())
+            .collect()
+    }
+
+    fn maps(plan: &ExportPlan) -> Vec<&str> {
+        plan.args
+            .windows(2)
+            .filter(|pair| pair[0] == "-map")
+            .map(|pair| pair[1].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn single_clip() {
+        let plan = build_export_plan(&request(vec![clip("c1", "a.mp4", 0, 0.0, 1.0, 4.0)]), &sources()).unwrap();
+        assert_eq!(inputs(&plan), vec!["a.mp4"]);
+        assert_eq!(plan.expected_duration, 3.0);
+        assert_eq!(
+            plan.filter_complex,
+            "[0:v]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30,setpts=PTS-STARTPTS,tpad=stop_mode=clone:stop_duration=3,trim=duration=3[v0]; \
+             [0:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo,apad,atrim=duration=3,asetpts=PTS-STARTPTS[a0]; \
+             [v0][a0]concat=n=1:v=1:a=1[vbase][outa]"
+        );
+        assert_eq!(maps(&plan), vec!["[vbase]", "[outa]"]);
+        assert_eq!(plan.args.last().unwrap(), "/tmp/out.mp4");
+        assert!(plan.args.windows(2).any(|pair| pair[0] == "-ss" && pair[1] == "1"));
+        assert!(plan.args.windows(2).any(|pair| pair[0] == "-t" && pair[1] == "3"));
+    }
+
+    #[test]
+    fn clips_are_sorted_by_start_time() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c2", "b.mp4", 0, 5.0, 0.0, 2.0),
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 5.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(inputs(&plan), vec!["a.mp4", "b.mp4"]);
+        assert!(plan.filter_complex.contains("[v0][a0][v1][a1]concat=n=2:v=1:a=1[vbase][outa]"));
+        assert_eq!(plan.expected_duration, 7.0);
+    }
+
+    #[test]
+    fn every_clip_contributes_audio() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0),
+                clip("c2", "b.mp4", 0, 2.0, 0.0, 2.0),
+                clip("c3", "a.mp4", 0, 4.0, 3.0, 5.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        for index in 0..3 {
+            assert!(plan.filter_complex.contains(&format!("[{}:a]aresample=48000", index)));
+        }
+        assert!(!plan.args.contains(&"0:a?".to_string()));
+    }
+
+    #[test]
+    fn silent_clips_get_generated_silence() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0),
+                clip("c2", "silent.mp4", 0, 2.0, 0.0, 1.5),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert!(!plan.filter_complex.contains("[1:a]"));
+        assert!(plan.filter_complex.contains(
+            "anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration=1.5,asetpts=PTS-STARTPTS[a1]"
+        ));
+    }
+
+    #[test]
+    fn clips_use_their_selected_or_default_audio_stream() {
+        let mut sources = sources();
+        let multi = sources.get_mut("b.mp4").unwrap();
+        multi.audio_streams = 3;
+        multi.default_audio_stream = 2;
+
+        let mut dubbed = clip("c2", "b.mp4", 0, 2.0, 0.0, 1.0);
+        dubbed.audio_stream = Some(1);
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "b.mp4", 0, 0.0, 0.0, 2.0),
+                dubbed.clone(),
+                clip("c3", "a.mp4", 0, 3.0, 0.0, 1.0),
+            ]),
+            &sources,
+        )
+        .unwrap();
+        assert!(plan.filter_complex.contains("[0:a:2]aresample=48000"));
+        assert!(plan.filter_complex.contains("[1:a:1]aresample=48000"));
+        assert!(plan.filter_complex.contains("[2:a]aresample=48000"));
+
+        dubbed.audio_stream = Some(3);
+        let err = build_export_plan(&request(vec![dubbed]), &sources).unwrap_err();
+        assert!(err.contains("uses audio stream 3 but b.mp4 has 3 audio stream(s)"), "{}", err);
+    }
+
+    #[test]
+    fn images_are_looped_into_video_segments() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "logo.png", 0, 0.0, 0.0, 3.0),
+                clip("o1", "logo.png", 1, 0.0, 0.0, 1.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        let first_input = plan.args.iter().position(|arg| arg == "-i").unwrap();
+        assert_eq!(plan.args[first_input - 4..=first_input + 1], ["-loop", "1", "-t", "3", "-i", "logo.png"]);
+        assert!(!plan.args.contains(&"-ss".to_string()));
+        assert!(plan.filter_complex.contains("[0:v]scale=1920:1080:force_original_aspect_ratio=decrease"));
+        assert!(plan.filter_complex.contains("anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration=3"));
+        assert!(plan.filter_complex.contains("[1:v]scale="));
+    }
+
+    #[test]
+    fn audio_clips_are_mixed_into_the_soundtrack() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 4.0),
+                clip("bed", "music.mp3", 0, 3.0, 10.0, 14.0),
+                clip("sting", "music.mp3", 1, 1.5, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(inputs(&plan), ["a.mp4", "music.mp3", "music.mp3"]);
+        assert_eq!(plan.expected_duration, 7.0);
+
+        // Audio clips never become video segments or overlays; the picture is padded to the end instead
+        assert!(!plan.filter_complex.contains("[1:v]"));
+        assert!(plan.filter_complex.contains("color=c=black:s=1920x1080:r=30:d=3,setsar=1[gvend]"));
+        assert!(plan.filter_complex.contains("concat=n=2:v=1:a=1[vbase][abase]"));
+        assert!(plan.filter_complex.contains(
+            "[1:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo,asetpts=PTS-STARTPTS,adelay=delays=1500:all=1[mix0]"
+        ));
+        assert!(plan.filter_complex.contains("[2:a]aresample=48000"));
+        assert!(plan.filter_complex.contains("adelay=delays=3000:all=1[mix1]"));
+        assert!(plan.filter_complex.contains(
+            "[abase][mix0][mix1]amix=inputs=3:duration=first:dropout_transition=0:normalize=0[outa]"
+        ));
+        assert!(plan.args.windows(2).any(|pair| pair[0] == "-map" && pair[1] == "[outa]"));
+    }
+
+    #[test]
+    fn gaps_are_filled_with_black_and_silence() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 1.0, 0.0, 2.0),
+                clip("c2", "b.mp4", 0, 5.0, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(plan.expected_duration, 7.0);
+        assert!(plan.filter_complex.contains("color=c=black:s=1920x1080:r=30:d=1,setsar=1[gv0]"));
+        assert!(plan.filter_complex.contains("color=c=black:s=1920x1080:r=30:d=2,setsar=1[gv1]"));
+        assert!(plan.filter_complex.contains(
+            "[gv0][ga0][v0][a0][gv1][ga1][v1][a1]concat=n=4:v=1:a=1[vbase][outa]"
+        ));
+    }
+
+    #[test]
+    fn adjacent_clips_have_no_gap() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0),
+                clip("c2", "b.mp4", 0, 2.0005, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert!(!plan.filter_complex.contains("color="));
+    }
+
+    #[test]
+    fn overlapping_main_track_clips_are_rejected() {
+        let err = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 3.0),
+                clip("c2", "b.mp4", 0, 2.0, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap_err();
+        assert!(err.contains("track 0"), "{}", err);
+        assert!(err.contains("overlap"), "{}", err);
+    }
+
+    #[test]
+    fn empty_requests_and_missing_main_track_are_rejected() {
+        assert!(build_export_plan(&request(vec![]), &sources()).is_err());
+        let err = build_export_plan(&request(vec![clip("o1", "cam.mp4", 1, 0.0, 0.0, 2.0)]), &sources())
+            .unwrap_err();
+        assert!(err.contains("Track 0"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_clip_ranges_are_rejected() {
+        let err = build_export_plan(&request(vec![clip("c1", "a.mp4", 0, 0.0, 2.0, 2.0)]), &sources())
+            .unwrap_err();
+        assert!(err.contains("empty trim range"), "{}", err);
+        let err = build_export_plan(&request(vec![clip("c1", "a.mp4", 0, -1.0, 0.0, 2.0)]), &sources())
+            .unwrap_err();
+        assert!(err.contains("invalid start time"), "{}", err);
+    }
+
+    #[test]
+    fn missing_sources_are_rejected() {
+        let err = build_export_plan(&request(vec![clip("c1", "nope.mp4", 0, 0.0, 0.0, 2.0)]), &sources())
+            .unwrap_err();
+        assert!(err.contains("nope.mp4"), "{}", err);
+        let err = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0),
+                clip("o1", "nope.mp4", 3, 0.0, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap_err();
+        assert!(err.contains("track 3"), "{}", err);
+    }
+
+    #[test]
+    fn single_overlay_is_placed_at_its_start_time() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 10.0),
+                clip("o1", "cam.mp4", 1, 2.5, 1.0, 4.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(inputs(&plan), vec!["a.mp4", "cam.mp4"]);
+        assert!(plan.filter_complex.contains("[1:v]scale=480:-2,setsar=1,setpts=PTS-STARTPTS+2.5/TB[ov0]"));
+        assert!(plan.filter_complex.contains(
+            "[vbase][ov0]overlay=38:H-h-32:eof_action=pass:enable='between(t,2.5,5.5)'[vov0]"
+        ));
+        assert_eq!(maps(&plan), vec!["[vov0]", "[outa]"]);
+    }
+
+    #[test]
+    fn overlay_audio_is_mixed_at_its_start_time() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 10.0),
+                clip("o1", "cam.mp4", 1, 2.5, 1.0, 4.0),
+                clip("o2", "silent.mp4", 2, 4.0, 0.0, 2.0),
+                clip("bed", "music.mp3", 0, 1.0, 0.0, 5.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(inputs(&plan), ["a.mp4", "cam.mp4", "silent.mp4", "music.mp3"]);
+        assert!(plan.filter_complex.contains("concat=n=1:v=1:a=1[vbase][abase]"));
+        assert!(plan.filter_complex.contains(
+            "[1:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo,asetpts=PTS-STARTPTS,adelay=delays=2500:all=1[ova0]"
+        ));
+        // Overlays without sound add nothing to the mix
+        assert!(!plan.filter_complex.contains("[2:a]"));
+        assert!(plan.filter_complex.contains(
+            "[abase][ova0][mix0]amix=inputs=3:duration=first:dropout_transition=0:normalize=0[outa]"
+        ));
+        assert_eq!(maps(&plan), ["[vov1]", "[outa]"]);
+    }
+
+    #[test]
+    fn multiple_overlays_are_chained() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 30.0),
+                clip("o2", "cam.mp4", 1, 20.0, 10.0, 15.0),
+                clip("o1", "cam.mp4", 1, 5.0, 0.0, 5.0),
+                clip("o3", "b.mp4", 1, 8.0, 0.0, 4.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(inputs(&plan), vec!["a.mp4", "cam.mp4", "b.mp4", "cam.mp4"]);
+        assert!(plan.filter_complex.contains("[vbase][ov0]overlay="));
+        assert!(plan.filter_complex.contains("enable='between(t,5,10)'[vov0]"));
+        assert!(plan.filter_complex.contains("[vov0][ov1]overlay="));
+        assert!(plan.filter_complex.contains("enable='between(t,8,12)'[vov1]"));
+        assert!(plan.filter_complex.contains("[vov1][ov2]overlay="));
+        assert!(plan.filter_complex.contains("enable='between(t,20,25)'[vov2]"));
+        assert_eq!(maps(&plan), vec!["[vov2]", "[outa]"]);
+    }
+
+    #[test]
+    fn higher_tracks_stack_over_lower_tracks() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 10.0),
+                clip("t3", "b.mp4", 3, 0.0, 0.0, 2.0),
+                clip("t1", "cam.mp4", 1, 1.0, 0.0, 2.0),
+                clip("t2", "a.mp4", 2, 0.0, 0.0, 2.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        // Inputs are added base first, then overlays from the lowest track up
+        assert_eq!(inputs(&plan), vec!["a.mp4", "cam.mp4", "a.mp4", "b.mp4"]);
+        assert!(plan.filter_complex.contains("[1:v]"));
+        assert!(plan.filter_complex.contains("[vov2]"));
+        assert_eq!(maps(&plan), vec!["[vov2]", "[outa]"]);
+    }
+
+    #[test]
+    fn overlays_past_the_main_track_extend_the_timeline() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 0.0, 0.0, 4.0),
+                clip("o1", "cam.mp4", 1, 3.0, 0.0, 3.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        assert_eq!(plan.expected_duration, 6.0);
+        assert!(plan.filter_complex.contains("color=c=black:s=1920x1080:r=30:d=2,setsar=1[gvend]"));
+        assert!(plan.filter_complex.contains("[v0][a0][gvend][gaend]concat=n=2"));
+    }
+
+    #[test]
+    fn overlay_transform_controls_layout() {
+        let mut overlay = clip("o1", "cam.mp4", 1, 0.0, 0.0, 2.0);
+        overlay.transform = Some(OverlayTransform {
+            anchor: OverlayAnchor::TopRight,
+            offset_x: 0.05,
+            offset_y: 0.1,
+            width: 0.5,
+            opacity: 0.8,
+            corner_radius: 0.1,
+            border: Some(OverlayBorder { width: 0.005, color: "white".to_string() }),
+        });
+        let mut req = request(vec![clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0), overlay]);
+        req.resolution = "4K".to_string();
+        let plan = build_export_plan(&req, &sources()).unwrap();
+        assert!(plan.filter_complex.contains(
+            "[1:v]scale=1920:-2,setsar=1,pad=iw+38:ih+38:19:19:color=white,format=yuva420p,colorchannelmixer=aa=0.8,geq="
+        ));
+        assert!(plan.filter_complex.contains("overlay=W-w-192:216:"));
+    }
+
+    #[test]
+    fn overlay_anchors() {
+        let cases = [
+            (OverlayAnchor::TopLeft, "overlay=192:108:"),
+            (OverlayAnchor::BottomLeft, "overlay=192:H-h-108:"),
+            (OverlayAnchor::BottomRight, "overlay=W-w-192:H-h-108:"),
+            (OverlayAnchor::Center, "overlay=(W-w)/2+192:(H-h)/2+108:"),
+        ];
+        for (anchor, expected) in cases {
+            let mut overlay = clip("o1", "cam.mp4", 1, 0.0, 0.0, 2.0);
+            overlay.transform = Some(OverlayTransform {
+                anchor,
+                offset_x: 0.1,
+                offset_y: 0.1,
+                ..OverlayTransform::default()
+            });
+            let plan = build_export_plan(
+                &request(vec![clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0), overlay]),
+                &sources(),
+            )
+            .unwrap();
+            assert!(plan.filter_complex.contains(expected), "{:?}: {}", anchor, plan.filter_complex);
+        }
+    }
+
+    #[test]
+    fn invalid_overlay_transforms_are_rejected() {
+        let invalid = [
+            OverlayTransform { width: 0.0, ..OverlayTransform::default() },
+            OverlayTransform { opacity: 1.5, ..OverlayTransform::default() },
+            OverlayTransform { corner_radius: 0.8, ..OverlayTransform::default() },
+            OverlayTransform {
+                border: Some(OverlayBorder { width: 0.01, color: "red:x=1".to_string() }),
+                ..OverlayTransform::default()
+            },
+        ];
+        for transform in invalid {
+            let mut overlay = clip("o1", "cam.mp4", 2, 0.0, 0.0, 2.0);
+            overlay.transform = Some(transform);
+            let err = build_export_plan(
+                &request(vec![clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0), overlay]),
+                &sources(),
+            )
+            .unwrap_err();
+            assert!(err.contains("track 2"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn output_format_selects_codecs() {
+        let mut req = request(vec![clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0)]);
+        req.format = "webm".to_string();
+        let plan = build_export_plan(&req, &sources()).unwrap();
+        assert!(plan.args.contains(&"libvpx-vp9".to_string()));
+        assert!(plan.args.contains(&"libopus".to_string()));
+
+        req.format = "mov".to_string();
+        let plan = build_export_plan(&req, &sources()).unwrap();
+        assert!(plan.args.contains(&"libx264".to_string()));
+        assert!(plan.args.contains(&"aac".to_string()));
+        assert_eq!(plan.requirements.muxer, "mov");
+    }
+
+    #[test]
+    fn requested_encoders_override_the_format_defaults() {
+        let mut req = request(vec![clip("c1", "a.mp4", 0, 0.0, 0.0, 2.0)]);
+        req.video_codec = Some("libopenh264".to_string());
+        let plan = build_export_plan(&req, &sources()).unwrap();
+        let codec_position = plan.args.iter().position(|arg| arg == "-c:v").unwrap();
+        assert_eq!(plan.args[codec_position..codec_position + 4], ["-c:v", "libopenh264", "-b:v", "8M"]);
+        assert!(!plan.args.contains(&"-crf".to_string()));
+        assert_eq!(plan.requirements.video_encoder, "libopenh264");
+        assert_eq!(plan.requirements.audio_encoder, "aac");
+    }
+
+    #[test]
+    fn requirements_list_the_filters_in_the_graph() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 1.0, 0.0, 2.0),
+                clip("o1", "cam.mp4", 1, 0.0, 0.0, 1.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        for filter in ["color", "anullsrc", "concat", "overlay", "setpts", "tpad", "trim"] {
+            assert!(plan.requirements.filters.contains(filter), "missing {}", filter);
+        }
+        assert_eq!(plan.requirements.muxer, "mp4");
+    }
+
+    #[test]
+    fn source_resolution_and_frame_rate_follow_track_zero() {
+        let mut sources = sources();
+        let portrait = sources.get_mut("b.mp4").unwrap();
+        portrait.width = 1081;
+        portrait.height = 1920;
+        portrait.frame_rate = 30000.0 / 1001.0;
+        sources.get_mut("a.mp4").unwrap().frame_rate = 24000.0 / 1001.0;
+        // Overlay sources don't change the output
+        sources.get_mut("cam.mp4").unwrap().frame_rate = 60.0;
+
+        let mut req = request(vec![
+            clip("c1", "b.mp4", 0, 0.0, 0.0, 2.0),
+            clip("c2", "a.mp4", 0, 3.0, 0.0, 1.0),
+            clip("o1", "cam.mp4", 1, 0.0, 0.0, 1.0),
+        ]);
+        req.resolution = "Source".to_string();
+        let plan = build_export_plan(&req, &sources).unwrap();
+        assert!(plan.filter_complex.contains("scale=1080:1920:force_original_aspect_ratio=decrease"));
+        assert!(plan.filter_complex.contains("fps=30000/1001"));
+        assert!(plan.filter_complex.contains("color=c=black:s=1080x1920:r=30000/1001:d=1"));
+
+        // Presets keep their size; unknown source sizes and rates fall back to 1080p30
+        req.resolution = "720p".to_string();
+        let plan = build_export_plan(&req, &sources).unwrap();
+        assert!(plan.filter_complex.contains("scale=1280:720:"));
+
+        for source in sources.values_mut() {
+            source.width = 0;
+            source.height = 0;
+            source.frame_rate = 0.0;
+        }
+        req.resolution = "Source".to_string();
+        let plan = build_export_plan(&req, &sources).unwrap();
+        assert!(plan.filter_complex.contains("scale=1920:1080:"));
+        assert!(plan.filter_complex.contains("fps=30,"));
+    }
+
+    #[test]
+    fn formats_frame_rates() {
+        assert_eq!(frame_rate_arg(30.0), "30");
+        assert_eq!(frame_rate_arg(29.97), "30000/1001");
+        assert_eq!(frame_rate_arg(24000.0 / 1001.0), "24000/1001");
+        assert_eq!(frame_rate_arg(59.94), "60000/1001");
+        assert_eq!(frame_rate_arg(12.5), "12.5");
+    }
+
+    #[test]
+    fn resolution_presets() {
+        assert_eq!(target_resolution("720p"), (1280, 720));
+        assert_eq!(target_resolution("1440p"), (2560, 1440));
+        assert_eq!(target_resolution("4K"), (3840, 2160));
+        assert_eq!(target_resolution("Source"), (1920, 1080));
+    }
+
+    #[test]
+    fn filter_complex_is_passed_once_in_args() {
+        let plan = build_export_plan(
+            &request(vec![
+                clip("c1", "a.mp4", 0, 2.0, 0.0, 4.0),
+                clip("o1", "cam.mp4", 1, 0.0, 0.0, 1.0),
+            ]),
+            &sources(),
+        )
+        .unwrap();
+        let position = plan.args.iter().position(|arg| arg == "-filter_complex").unwrap();
+        assert_eq!(plan.args[position + 1], plan.filter_complex);
+        assert_eq!(plan.args.iter().filter(|arg| *arg == "-filter_complex").count(), 1);
+    }
+}
@@ -0,0 +1,65 @@
+//! Resolves an export request against the files on disk and the FFmpeg build in use
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{build_export_plan, ExportPlan, ExportSource};
+use crate::error::{ClipforgeError, ErrorKind};
+use crate::ffmpeg_capabilities::CapabilityCache;
+use crate::ffmpeg_locator::{Tool, ToolLocator};
+use crate::{media, ExportRequest, VideoMetadata};
+
+/// Resolves sources, builds the FFmpeg plan, checks it against the FFmpeg build and locates FFmpeg
+/// Shared by export_video, the render queue and the headless renderer
+pub fn prepare_export(
+    request: &ExportRequest,
+    clips_data: &[VideoMetadata],
+    locator: &dyn ToolLocator,
+    capabilities: &CapabilityCache,
+) -> Result<(ExportPlan, PathBuf), ClipforgeError> {
+    log::info!("Exporting {} clips", request.clips.len());
+
+    // Resolve each referenced source file once, probing its size, frame rate and audio streams
+    let mut sources: HashMap<String, ExportSource> = HashMap::new();
+    for clip in &request.clips {
+        if sources.contains_key(&clip.clip_id) {
+            continue;
+        }
+        if let Some(source_clip) = clips_data.iter().find(|c| c.path == clip.clip_id) {
+            if !Path::new(&source_clip.path).is_file() {
+                return Err(ClipforgeError::new(
+                    ErrorKind::FileNotFound,
+                    format!("Source file is missing: {} (relink it from the media library)", source_clip.path),
+                )
+                .with_path(&source_clip.path));
+            }
+            // Fall back to the metadata the frontend sent if the file can't be probed now
+            let metadata = media::extract_video_metadata(&source_clip.path)
+                .unwrap_or_else(|_| source_clip.clone());
+            let (width, height) = metadata.display_size();
+            sources.insert(clip.clip_id.clone(), ExportSource {
+                path: source_clip.path.clone(),
+                kind: metadata.media_kind,
+                audio_streams: metadata.audio_streams.len(),
+                default_audio_stream: metadata.default_audio_stream().unwrap_or(0),
+                width,
+                height,
+                frame_rate: metadata.avg_frame_rate,
+            });
+        }
+    }
+
+    // Build FFmpeg arguments and filter graph from the timeline
+    let plan = build_export_plan(request, &sources).map_err(|e| ClipforgeError::new(ErrorKind::InvalidInput, e))?;
+
+    log::debug!("FFmpeg args: {:?}", plan.args);
+
+    // Make sure this FFmpeg build has every encoder, muxer and filter the plan uses
+    capabilities
+        .get(locator, false)?
+        .check(&plan.requirements)
+        .map_err(|e| ClipforgeError::new(ErrorKind::UnsupportedCodec, e))?;
+    let ffmpeg = locator.locate(Tool::Ffmpeg)?;
+
+    Ok((plan, ffmpeg.path))
+}
@@ -0,0 +1,252 @@
+//! Parsing of FFmpeg `-progress` output into structured progress reports
+//!
+//! FFmpeg writes blocks of `key=value` lines, each terminated by
+//! `progress=continue` (or `progress=end` for the last one).
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Phase of an export, reported with every progress event
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStage {
+    /// FFmpeg has started but has not reported any progress yet
+    Starting,
+    /// Frames are being encoded
+    Encoding,
+    /// Encoding has ended; FFmpeg is writing the container trailer
+    Finalizing,
+    /// The output file is complete
+    Complete,
+}
+
+/// Payload of the `export_progress` event
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ExportProgress {
+    pub job_id: String,
+    pub stage: ExportStage,
+    /// 0-100; held at 99 until FFmpeg has exited successfully
+    pub percent: u32,
+    /// Position reached in the output timeline, in seconds
+    pub out_time: f64,
+    /// Wall-clock time since FFmpeg started, in seconds
+    pub elapsed: f64,
+    /// Estimated seconds until the export completes
+    pub eta: Option<f64>,
+    /// Encoding frames per second
+    pub fps: Option<f64>,
+    /// Encoding speed as a multiple of real time
+    pub speed: Option<f64>,
+    /// Current output bitrate in kbit/s
+    pub bitrate_kbps: Option<f64>,
+    /// Bytes written to the output file so far
+    pub output_size: u64,
+}
+
+impl ExportProgress {
+    /// Progress before FFmpeg has reported anything
+    pub fn starting(job_id: String) -> Self {
+        Self {
+            job_id,
+            stage: ExportStage::Starting,
+            percent: 0,
+            out_time: 0.0,
+            elapsed: 0.0,
+            eta: None,
+            fps: None,
+            speed: None,
+            bitrate_kbps: None,
+            output_size: 0,
+        }
+    }
+
+    /// Final progress once FFmpeg has exited successfully
+    pub fn complete(mut self, elapsed: Duration, output_size: u64) -> Self {
+        self.stage = ExportStage::Complete;
+        self.percent = 100;
+        self.elapsed = elapsed.as_secs_f64();
+        self.eta = Some(0.0);
+        self.output_size = output_size;
+        self
+    }
+}
+
+/// One complete `-progress` block
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressBlock {
+    pub out_time: f64,
+    pub fps: Option<f64>,
+    pub speed: Option<f64>,
+    pub bitrate_kbps: Option<f64>,
+    pub total_size: Option<u64>,
+    /// True for the final block (`progress=end`)
+    pub end: bool,
+}
+
+impl ProgressBlock {
+    /// Converts the block into an event payload for a timeline of `expected_duration` seconds
+    pub fn to_progress(&self, job_id: &str, expected_duration: f64, elapsed: Duration) -> ExportProgress {
+        let fraction = if expected_duration > 0.0 {
+            (self.out_time / expected_duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let elapsed = elapsed.as_secs_f64();
+        let remaining = (expected_duration - self.out_time).max(0.0);
+
+        // Prefer FFmpeg's speed; fall back to extrapolating from elapsed time
+        let eta = match self.speed {
+            Some(speed) if speed > 0.0 => Some(remaining / speed),
+            _ if fraction > 0.0 => Some(elapsed * (1.0 - fraction) / fraction),
+            _ => None,
+        };
+
+        ExportProgress {
+            job_id: job_id.to_string(),
+            stage: if self.end { ExportStage::Finalizing } else { ExportStage::Encoding },
+            percent: (fraction * 100.0).min(99.0) as u32,
+            out_time: self.out_time,
+            elapsed,
+            eta,
+            fps: self.fps,
+            speed: self.speed,
+            bitrate_kbps: self.bitrate_kbps,
+            output_size: self.total_size.unwrap_or(0),
+        }
+    }
+}
+
+/// Accumulates `-progress` lines into blocks
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: ProgressBlock,
+}
+
+impl ProgressParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the line is part of FFmpeg's `-progress` output rather than a log message
+    pub fn is_progress_line(line: &str) -> bool {
+        const KEYS: &[&str] = &[
+            "frame", "fps", "stream_", "bitrate", "total_size", "out_time", "dup_frames",
+            "drop_frames", "speed", "progress",
+        ];
+        match line.split_once('=') {
+            Some((key, _)) => KEYS.iter().any(|known| key.starts_with(known)),
+            None => false,
+        }
+    }
+
+    /// Feeds one line of output; returns the block when a `progress=` line completes it
+    pub fn feed(&mut self, line: &str) -> Option<ProgressBlock> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            // out_time_ms is also in microseconds (a long-standing FFmpeg quirk)
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(time_us) = value.parse::<i64>() {
+                    self.current.out_time = (time_us.max(0) as f64) / 1_000_000.0;
+                }
+            }
+            "fps" => self.current.fps = value.parse().ok(),
+            "speed" => self.current.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "bitrate" => {
+                self.current.bitrate_kbps = value.trim_end_matches("kbits/s").trim().parse().ok()
+            }
+            "total_size" => self.current.total_size = value.parse().ok(),
+            "progress" => {
+                let mut block = std::mem::take(&mut self.current);
+                block.end = value == "end";
+                // Carry the position forward in case the next block omits it
+                self.current.out_time = block.out_time;
+                return Some(block);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "frame=120
+fps=59.94
+stream_0_0_q=28.0
+bitrate=1523.4kbits/s
+total_size=952064
+out_time_us=5000000
+out_time_ms=5000000
+out_time=00:00:05.000000
+dup_frames=0
+drop_frames=0
+speed=2.5x
+progress=continue";
+
+    #[test]
+    fn parses_a_progress_block() {
+        let mut parser = ProgressParser::new();
+        let blocks: Vec<ProgressBlock> = BLOCK.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(
+            blocks,
+            vec![ProgressBlock {
+                out_time: 5.0,
+                fps: Some(59.94),
+                speed: Some(2.5),
+                bitrate_kbps: Some(1523.4),
+                total_size: Some(952064),
+                end: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn handles_missing_values_and_end_of_stream() {
+        let mut parser = ProgressParser::new();
+        let output = "bitrate=N/A\ntotal_size=N/A\nout_time_us=N/A\nspeed=N/A\nprogress=continue\n\
+                      out_time_us=2000000\nprogress=end";
+        let blocks: Vec<ProgressBlock> = output.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].bitrate_kbps, None);
+        assert_eq!(blocks[0].total_size, None);
+        assert_eq!(blocks[0].speed, None);
+        assert!(!blocks[0].end);
+        assert_eq!(blocks[1].out_time, 2.0);
+        assert!(blocks[1].end);
+    }
+
+    #[test]
+    fn recognizes_progress_lines() {
+        assert!(ProgressParser::is_progress_line("out_time_us=123"));
+        assert!(ProgressParser::is_progress_line("stream_0_0_q=28.0"));
+        assert!(ProgressParser::is_progress_line("progress=end"));
+        assert!(!ProgressParser::is_progress_line("[libx264 @ 0x1] using cpu capabilities"));
+        assert!(!ProgressParser::is_progress_line("Error opening input file a.mp4."));
+    }
+
+    #[test]
+    fn computes_percent_and_eta() {
+        let block = ProgressBlock { out_time: 5.0, speed: Some(2.0), ..ProgressBlock::default() };
+        let progress = block.to_progress("job", 10.0, Duration::from_secs(3));
+        assert_eq!(progress.stage, ExportStage::Encoding);
+        assert_eq!(progress.percent, 50);
+        assert_eq!(progress.eta, Some(2.5));
+        assert_eq!(progress.elapsed, 3.0);
+
+        // Without a speed the ETA is extrapolated from elapsed time
+        let block = ProgressBlock { out_time: 2.5, ..ProgressBlock::default() };
+        let progress = block.to_progress("job", 10.0, Duration::from_secs(4));
+        assert_eq!(progress.eta, Some(12.0));
+
+        // Percent stays below 100 until the export has actually completed
+        let block = ProgressBlock { out_time: 10.0, end: true, ..ProgressBlock::default() };
+        let progress = block.to_progress("job", 10.0, Duration::from_secs(4));
+        assert_eq!(progress.stage, ExportStage::Finalizing);
+        assert_eq!(progress.percent, 99);
+        assert_eq!(progress.clone().complete(Duration::from_secs(5), 42).percent, 100);
+    }
+}
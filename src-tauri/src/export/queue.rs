@@ -0,0 +1,285 @@
+//! Persistent render queue for batching exports
+//!
+//! Jobs are kept in order and started in the background, up to a configurable
+//! number at a time. The queue is saved to disk after every change so pending
+//! renders survive an app restart.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs_util::write_atomically;
+use crate::{ExportRequest, VideoMetadata};
+
+/// Lifecycle of a queued export
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueJobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// An export waiting in, running from, or finished by the render queue
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedExport {
+    pub id: String,
+    pub request: ExportRequest,
+    pub clips_data: Vec<VideoMetadata>,
+    pub status: QueueJobStatus,
+    pub error: Option<String>,
+    pub created_at: u64, // Unix timestamp in seconds
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueState {
+    concurrency: usize,
+    jobs: Vec<QueuedExport>,
+}
+
+impl Default for QueueState {
+    fn default() -> Self {
+        Self { concurrency: 1, jobs: Vec::new() }
+    }
+}
+
+/// The render queue, managed as Tauri state
+pub struct RenderQueue {
+    path: PathBuf,
+    state: Mutex<QueueState>,
+}
+
+impl RenderQueue {
+    /// Loads the queue from disk, starting empty if the file is missing or unreadable
+    /// Jobs that were running when the app quit are put back to pending
+    pub fn load(path: PathBuf) -> Self {
+        let mut state: QueueState = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        for job in &mut state.jobs {
+            if job.status == QueueJobStatus::Running {
+                job.status = QueueJobStatus::Pending;
+            }
+        }
+        Self { path, state: Mutex::new(state) }
+    }
+
+    /// Adds an export to the end of the queue
+    pub fn enqueue(&self, id: String, request: ExportRequest, clips_data: Vec<VideoMetadata>) -> QueuedExport {
+        let job = QueuedExport {
+            id,
+            request,
+            clips_data,
+            status: QueueJobStatus::Pending,
+            error: None,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        let _ = self.update(|state| {
+            state.jobs.push(job.clone());
+            Ok(())
+        });
+        job
+    }
+
+    pub fn list(&self) -> Vec<QueuedExport> {
+        self.state.lock().unwrap().jobs.clone()
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.state.lock().unwrap().concurrency
+    }
+
+    /// Sets how many queued exports may render at the same time
+    pub fn set_concurrency(&self, concurrency: usize) -> Result<(), String> {
+        if concurrency == 0 {
+            return Err("Export concurrency must be at least 1".to_string());
+        }
+        self.update(|state| {
+            state.concurrency = concurrency;
+            Ok(())
+        })
+    }
+
+    /// Moves a job to a new position in the queue (clamped to the end)
+    pub fn reorder(&self, id: &str, position: usize) -> Result<(), String> {
+        self.update(|state| {
+            let index = find_job(&state.jobs, id)?;
+            let job = state.jobs.remove(index);
+            let position = position.min(state.jobs.len());
+            state.jobs.insert(position, job);
+            Ok(())
+        })
+    }
+
+    /// Removes a job that is not currently rendering
+    pub fn remove(&self, id: &str) -> Result<QueuedExport, String> {
+        let mut removed = None;
+        self.update(|state| {
+            let index = find_job(&state.jobs, id)?;
+            if state.jobs[index].status == QueueJobStatus::Running {
+                return Err(format!("Export {} is rendering; cancel it before removing it", id));
+            }
+            removed = Some(state.jobs.remove(index));
+            Ok(())
+        })?;
+        Ok(removed.unwrap())
+    }
+
+    /// Marks as many pending jobs as running as the concurrency limit allows, in queue order
+    /// Returns the jobs that should be started now
+    pub fn take_startable(&self) -> Vec<QueuedExport> {
+        let mut started = Vec::new();
+        let _ = self.update(|state| {
+            let running = state.jobs.iter().filter(|job| job.status == QueueJobStatus::Running).count();
+            let free_slots = state.concurrency.saturating_sub(running);
+            for job in state
+                .jobs
+                .iter_mut()
+                .filter(|job| job.status == QueueJobStatus::Pending)
+                .take(free_slots)
+            {
+                job.status = QueueJobStatus::Running;
+                started.push(job.clone());
+            }
+            Ok(())
+        });
+        started
+    }
+
+    /// Records the outcome of a job and returns its updated entry
+    pub fn finish(&self, id: &str, status: QueueJobStatus, error: Option<String>) -> Option<QueuedExport> {
+        let mut finished = None;
+        let _ = self.update(|state| {
+            let index = find_job(&state.jobs, id)?;
+            let job = &mut state.jobs[index];
+            job.status = status;
+            job.error = error;
+            finished = Some(job.clone());
+            Ok(())
+        });
+        finished
+    }
+
+    /// Applies a change to the queue and saves it to disk
+    fn update(&self, change: impl FnOnce(&mut QueueState) -> Result<(), String>) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        change(&mut state)?;
+        if let Err(e) = self.persist(&state) {
+            log::warn!("Failed to save export queue: {}", e);
+        }
+        Ok(())
+    }
+
+    fn persist(&self, state: &QueueState) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create queue directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(state)
+            .map_err(|e| format!("Failed to serialize export queue: {}", e))?;
+        write_atomically(&self.path, json).map_err(|e| format!("Failed to write export queue: {}", e))
+    }
+}
+
+fn find_job(jobs: &[QueuedExport], id: &str) -> Result<usize, String> {
+    jobs.iter()
+        .position(|job| job.id == id)
+        .ok_or_else(|| format!("No queued export with ID: {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn queue_path(name: &str) -> PathBuf {
+        temp_dir(&format!("queue_{}", name)).join("queue.json")
+    }
+
+    fn request(output_path: &str) -> ExportRequest {
+        ExportRequest {
+            clips: Vec::new(),
+            output_path: output_path.to_string(),
+            resolution: "1080p".to_string(),
+            format: "mp4".to_string(),
+            video_codec: None,
+            audio_codec: None,
+        }
+    }
+
+    fn ids(queue: &RenderQueue) -> Vec<String> {
+        queue.list().into_iter().map(|job| job.id).collect()
+    }
+
+    #[test]
+    fn starts_jobs_up_to_the_concurrency_limit() {
+        let queue = RenderQueue::load(queue_path("concurrency"));
+        for id in ["a", "b", "c"] {
+            queue.enqueue(id.to_string(), request(id), Vec::new());
+        }
+
+        let started: Vec<String> = queue.take_startable().into_iter().map(|job| job.id).collect();
+        assert_eq!(started, vec!["a"]);
+        assert!(queue.take_startable().is_empty());
+
+        queue.set_concurrency(2).unwrap();
+        let started: Vec<String> = queue.take_startable().into_iter().map(|job| job.id).collect();
+        assert_eq!(started, vec!["b"]);
+
+        queue.finish("a", QueueJobStatus::Completed, None);
+        let started: Vec<String> = queue.take_startable().into_iter().map(|job| job.id).collect();
+        assert_eq!(started, vec!["c"]);
+        assert!(queue.set_concurrency(0).is_err());
+    }
+
+    #[test]
+    fn reorders_and_removes_jobs() {
+        let queue = RenderQueue::load(queue_path("reorder"));
+        for id in ["a", "b", "c"] {
+            queue.enqueue(id.to_string(), request(id), Vec::new());
+        }
+
+        queue.reorder("c", 0).unwrap();
+        assert_eq!(ids(&queue), vec!["c", "a", "b"]);
+        queue.reorder("c", 10).unwrap();
+        assert_eq!(ids(&queue), vec!["a", "b", "c"]);
+        assert!(queue.reorder("missing", 0).is_err());
+
+        // Running jobs can't be removed
+        queue.take_startable();
+        assert!(queue.remove("a").is_err());
+        assert_eq!(queue.remove("b").unwrap().id, "b");
+        assert_eq!(ids(&queue), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn persists_across_reloads_and_requeues_interrupted_jobs() {
+        let path = queue_path("persist");
+        {
+            let queue = RenderQueue::load(path.clone());
+            queue.set_concurrency(3).unwrap();
+            queue.enqueue("a".to_string(), request("a.mp4"), Vec::new());
+            queue.enqueue("b".to_string(), request("b.mp4"), Vec::new());
+            queue.take_startable();
+            queue.finish("b", QueueJobStatus::Failed, Some("boom".to_string()));
+        }
+
+        let queue = RenderQueue::load(path);
+        let jobs = queue.list();
+        assert_eq!(queue.concurrency(), 3);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].status, QueueJobStatus::Pending);
+        assert_eq!(jobs[0].request.output_path, "a.mp4");
+        assert_eq!(jobs[1].status, QueueJobStatus::Failed);
+        assert_eq!(jobs[1].error.as_deref(), Some("boom"));
+    }
+}
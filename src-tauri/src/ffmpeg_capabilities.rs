@@ -0,0 +1,269 @@
+//! Detects which encoders, muxers and filters the resolved FFmpeg provides,
+//! so exports can be checked before they start rendering
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+
+use crate::error::ClipforgeError;
+use crate::export::ExportRequirements;
+use crate::ffmpeg_locator::{Tool, ToolLocator};
+
+/// Encoders to suggest, in order of preference, when an export's encoder is missing
+const ENCODER_FALLBACKS: &[(&str, &[&str])] = &[
+    ("libx264", &["libopenh264", "h264_videotoolbox", "h264_mf", "mpeg4"]),
+    ("libvpx-vp9", &["libvpx", "libsvtav1"]),
+    ("libopus", &["opus", "libvorbis"]),
+    ("aac", &["libfdk_aac", "aac_at"]),
+];
+
+/// What an FFmpeg binary can do
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct FfmpegCapabilities {
+    pub ffmpeg_path: String,
+    pub version: Option<String>,
+    pub encoders: BTreeSet<String>,
+    pub muxers: BTreeSet<String>,
+    pub filters: BTreeSet<String>,
+    /// Export encoders (and their fallbacks) present in the FFmpeg libraries linked into the app
+    pub library_encoders: BTreeSet<String>,
+}
+
+impl FfmpegCapabilities {
+    /// Runs `-encoders`, `-muxers` and `-filters` on the located FFmpeg
+    pub fn probe(locator: &dyn ToolLocator) -> Result<Self, ClipforgeError> {
+        let info = locator.info(Tool::Ffmpeg)?;
+        let ffmpeg = locator.locate(Tool::Ffmpeg)?;
+        let list = |flag: &str| -> Result<String, ClipforgeError> {
+            let output = ffmpeg.run(["-hide_banner", flag], &format!("FFmpeg {} failed", flag))?;
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        };
+
+        Ok(Self {
+            ffmpeg_path: info.path,
+            version: info.version,
+            encoders: parse_table(&list("-encoders")?),
+            muxers: parse_table(&list("-muxers")?),
+            filters: parse_filters(&list("-filters")?),
+            library_encoders: linked_library_encoders(),
+        })
+    }
+
+    /// First available replacement for a missing encoder
+    pub fn fallback_for(&self, encoder: &str) -> Option<&'static str> {
+        ENCODER_FALLBACKS
+            .iter()
+            .find(|(name, _)| *name == encoder)
+            .and_then(|(_, fallbacks)| fallbacks.iter().find(|fallback| self.encoders.contains(**fallback)))
+            .copied()
+    }
+
+    /// Checks that this FFmpeg can render an export, suggesting fallbacks for missing encoders
+    pub fn check(&self, requirements: &ExportRequirements) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        let encoders = [
+            ("video", "video_codec", &requirements.video_encoder),
+            ("audio", "audio_codec", &requirements.audio_encoder),
+        ];
+        for (kind, field, encoder) in encoders {
+            if self.encoders.contains(encoder) {
+                continue;
+            }
+            problems.push(match self.fallback_for(encoder) {
+                Some(fallback) => format!(
+                    "{} encoder {} is not available (set {} to \"{}\" to use it instead)",
+                    kind, encoder, field, fallback
+                ),
+                None => format!("{} encoder {} is not available", kind, encoder),
+            });
+        }
+
+        if !self.muxers.contains(&requirements.muxer) {
+            problems.push(format!("muxer {} is not available", requirements.muxer));
+        }
+
+        let missing_filters: Vec<&str> = requirements
+            .filters
+            .iter()
+            .filter(|filter| !self.filters.contains(*filter))
+            .map(String::as_str)
+            .collect();
+        if !missing_filters.is_empty() {
+            problems.push(format!("filters not available: {}", missing_filters.join(", ")));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "FFmpeg at {} cannot render this export: {}",
+                self.ffmpeg_path,
+                problems.join("; ")
+            ))
+        }
+    }
+}
+
+/// Capabilities of the last probed FFmpeg, managed as Tauri state
+#[derive(Default)]
+pub struct CapabilityCache {
+    cached: Mutex<Option<FfmpegCapabilities>>,
+}
+
+impl CapabilityCache {
+    /// Returns the cached capabilities, probing again when `refresh` is set or a different
+    /// FFmpeg binary is now in use
+    pub fn get(&self, locator: &dyn ToolLocator, refresh: bool) -> Result<FfmpegCapabilities, ClipforgeError> {
+        let ffmpeg_path = locator.locate(Tool::Ffmpeg)?.path.to_string_lossy().to_string();
+        let mut cached = self.cached.lock().unwrap();
+        if !refresh
+            && let Some(capabilities) = cached.as_ref()
+            && capabilities.ffmpeg_path == ffmpeg_path
+        {
+            return Ok(capabilities.clone());
+        }
+
+        let capabilities = FfmpegCapabilities::probe(locator)?;
+        log::info!(
+            "FFmpeg capabilities: {} encoders, {} muxers, {} filters",
+            capabilities.encoders.len(),
+            capabilities.muxers.len(),
+            capabilities.filters.len()
+        );
+        *cached = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+}
+
+/// Names from `-encoders` or `-muxers` output: a legend, a `---` rule, then one
+/// `<flags> <name> <description>` row per entry (names may be comma-separated)
+fn parse_table(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .skip_while(|line| {
+            let line = line.trim();
+            line.is_empty() || !line.chars().all(|c| c == '-')
+        })
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .flat_map(|names| names.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Names from `-filters` output, whose rows look like ` TSC adelay  A->A  Delay ...`
+fn parse_filters(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace().skip(1);
+            let name = columns.next()?;
+            columns.next()?.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn linked_library_encoders() -> BTreeSet<String> {
+    ENCODER_FALLBACKS
+        .iter()
+        .flat_map(|(encoder, fallbacks)| std::iter::once(encoder).chain(fallbacks.iter()))
+        .filter(|name| ffmpeg::encoder::find_by_name(name).is_some())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODERS: &str = "Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ------
+ V....D libopenh264          OpenH264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ V....D libvpx-vp9           libvpx VP9 (codec vp9)
+ A....D aac                  AAC (Advanced Audio Coding)
+ A....D opus                 Opus (codec opus)
+";
+
+    const MUXERS: &str = "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ ---
+  E mov             QuickTime / MOV
+  E mp4             MP4 (MPEG-4 Part 14)
+  E webm            WebM
+";
+
+    const FILTERS: &str = "Filters:
+  T.. = Timeline support
+  .S. = Slice threading
+  A = Audio input/output
+  | = Source or sink filter
+ ... anullsrc          |->A       Null audio source, return empty audio frames.
+ TSC overlay           VV->V      Overlay a video source on top of the input.
+ ..C scale             V->V       Scale the input video size and/or convert the image format.
+";
+
+    fn capabilities() -> FfmpegCapabilities {
+        FfmpegCapabilities {
+            ffmpeg_path: "/usr/bin/ffmpeg".to_string(),
+            encoders: parse_table(ENCODERS),
+            muxers: parse_table(MUXERS),
+            filters: parse_filters(FILTERS),
+            ..Default::default()
+        }
+    }
+
+    fn requirements(video_encoder: &str, audio_encoder: &str, muxer: &str) -> ExportRequirements {
+        ExportRequirements {
+            video_encoder: video_encoder.to_string(),
+            audio_encoder: audio_encoder.to_string(),
+            muxer: muxer.to_string(),
+            filters: ["overlay", "scale"].into_iter().map(String::from).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_encoder_muxer_and_filter_lists() {
+        let capabilities = capabilities();
+        assert_eq!(
+            capabilities.encoders.iter().collect::<Vec<_>>(),
+            ["aac", "libopenh264", "libvpx-vp9", "opus"]
+        );
+        assert_eq!(capabilities.muxers.iter().collect::<Vec<_>>(), ["mov", "mp4", "webm"]);
+        assert_eq!(capabilities.filters.iter().collect::<Vec<_>>(), ["anullsrc", "overlay", "scale"]);
+    }
+
+    #[test]
+    fn accepts_exports_the_build_supports() {
+        assert_eq!(capabilities().check(&requirements("libvpx-vp9", "opus", "webm")), Ok(()));
+    }
+
+    #[test]
+    fn suggests_fallbacks_for_missing_encoders() {
+        let capabilities = capabilities();
+        assert_eq!(capabilities.fallback_for("libx264"), Some("libopenh264"));
+        assert_eq!(capabilities.fallback_for("libopus"), Some("opus"));
+        assert_eq!(capabilities.fallback_for("aac"), None);
+
+        let err = capabilities.check(&requirements("libx264", "libopus", "mp4")).unwrap_err();
+        assert!(err.contains("video encoder libx264 is not available"), "{}", err);
+        assert!(err.contains("set video_codec to \"libopenh264\""), "{}", err);
+        assert!(err.contains("set audio_codec to \"opus\""), "{}", err);
+    }
+
+    #[test]
+    fn reports_missing_muxers_and_filters() {
+        let mut required = requirements("libopenh264", "aac", "matroska");
+        required.filters.insert("geq".to_string());
+        let err = capabilities().check(&required).unwrap_err();
+        assert!(err.contains("muxer matroska is not available"), "{}", err);
+        assert!(err.contains("filters not available: geq"), "{}", err);
+    }
+}
@@ -0,0 +1,449 @@
+//! Locates the FFmpeg and FFprobe binaries used by every command
+//!
+//! Resolution order:
+//! 1. The bundled sidecar (next to the app executable on macOS, in the resource directory,
+//!    or in `src-tauri/binaries` in development)
+//! 2. A user-configured path, saved in the app config directory
+//! 3. `ffmpeg` / `ffprobe` on `PATH`
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::{ClipforgeError, ErrorKind};
+
+/// A command-line tool from the FFmpeg suite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tool {
+    Ffmpeg,
+    Ffprobe,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+        }
+    }
+}
+
+/// Where a binary was found
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarySource {
+    Bundled,
+    Configured,
+    System,
+}
+
+/// A located binary
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ResolvedBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+}
+
+impl ResolvedBinary {
+    /// Starts building a command that runs this binary
+    pub fn command(&self) -> Command {
+        Command::new(&self.path)
+    }
+
+    /// Runs this binary to completion; a failed exit becomes an `FfmpegFailed` error
+    /// described by `action` and carrying the binary's stderr
+    pub fn run<I, S>(&self, args: I, action: &str) -> Result<Output, ClipforgeError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let output = self.command().args(args).output().map_err(|e| {
+            ClipforgeError::new(ErrorKind::FfmpegFailed, format!("{}: failed to run {}: {}", action, self.path.display(), e))
+        })?;
+        if !output.status.success() {
+            return Err(ClipforgeError::ffmpeg_failed(action, &output.stderr));
+        }
+        Ok(output)
+    }
+}
+
+/// What the locator picked for a tool, as reported to the frontend
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolInfo {
+    pub name: String,
+    pub path: String,
+    pub source: BinarySource,
+    /// Version from `-version`, e.g. "7.1"
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct LocatorSettings {
+    ffmpeg_path: Option<PathBuf>,
+}
+
+/// Resolves FFmpeg tools to binaries
+/// The export pipeline and capability checks only need this, so they can run against a
+/// locator other than the app's
+pub trait ToolLocator: Send + Sync {
+    /// Finds a tool's binary
+    fn locate(&self, tool: Tool) -> Result<ResolvedBinary, ClipforgeError>;
+
+    /// Locates a tool and reads its version
+    fn info(&self, tool: Tool) -> Result<ToolInfo, ClipforgeError> {
+        let resolved = self.locate(tool)?;
+        let version = resolved
+            .command()
+            .arg("-version")
+            .output()
+            .ok()
+            .and_then(|output| parse_version(&String::from_utf8_lossy(&output.stdout)));
+
+        Ok(ToolInfo {
+            name: tool.name().to_string(),
+            path: resolved.path.to_string_lossy().to_string(),
+            source: resolved.source,
+            version,
+        })
+    }
+}
+
+/// Finds FFmpeg binaries, managed as Tauri state
+pub struct FfmpegLocator {
+    /// Directories searched for the bundled sidecar, in order
+    bundled_dirs: Vec<PathBuf>,
+    /// Target triple appended to sidecar names in development (`ffmpeg-<triple>`)
+    target_triple: Option<String>,
+    /// Where the user-configured path is saved
+    settings_path: Option<PathBuf>,
+    /// Directories searched last, normally `PATH`
+    search_path: Option<OsString>,
+    configured: Mutex<Option<PathBuf>>,
+    /// FFmpeg that replaces every other candidate, e.g. from `--ffmpeg` on the command line
+    override_ffmpeg: Option<PathBuf>,
+    resolved: Mutex<HashMap<Tool, ResolvedBinary>>,
+}
+
+impl FfmpegLocator {
+    pub fn new(
+        bundled_dirs: Vec<PathBuf>,
+        target_triple: Option<String>,
+        settings_path: Option<PathBuf>,
+        search_path: Option<OsString>,
+    ) -> Self {
+        let configured = settings_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str::<LocatorSettings>(&json).ok())
+            .and_then(|settings| settings.ffmpeg_path);
+
+        Self {
+            bundled_dirs,
+            target_triple,
+            settings_path,
+            search_path,
+            configured: Mutex::new(configured),
+            override_ffmpeg: None,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Uses only `ffmpeg` (and the FFprobe beside it): the bundled sidecar, configured path
+    /// and `PATH` are no longer searched
+    pub fn with_override(mut self, ffmpeg: PathBuf) -> Result<Self, ClipforgeError> {
+        if !ffmpeg.is_file() {
+            return Err(ClipforgeError::new(
+                ErrorKind::FileNotFound,
+                format!("FFmpeg binary not found at: {:?}", ffmpeg),
+            )
+            .with_path(&ffmpeg));
+        }
+        self.override_ffmpeg = Some(ffmpeg);
+        self.clear_cache();
+        Ok(self)
+    }
+
+    /// Locator for the running app: sidecars next to the executable (on macOS) or in the
+    /// resource directory, then `binaries/` under the working directory in development,
+    /// then the configured path and `PATH`
+    pub fn for_app(app: &tauri::AppHandle) -> Self {
+        let mut bundled_dirs: Vec<PathBuf> = sidecar_exe_dir().into_iter().collect();
+        if let Ok(resource_dir) = app.path().resource_dir() {
+            bundled_dirs.push(resource_dir);
+        }
+        if cfg!(dev) {
+            if let Ok(current) = env::current_dir() {
+                bundled_dirs.push(current.join("src-tauri").join("binaries"));
+                bundled_dirs.push(current.join("binaries"));
+            }
+        }
+        let target_triple = tauri::utils::platform::target_triple().ok();
+        let settings_path = app.path().app_config_dir().ok().map(|dir| dir.join("ffmpeg.json"));
+        Self::new(bundled_dirs, target_triple, settings_path, env::var_os("PATH"))
+    }
+
+    /// Locator for running without the app (the headless renderer): sidecars next to the
+    /// executable (on macOS), then `PATH`; nothing is read from or saved to the app's settings
+    pub fn standalone() -> Self {
+        let bundled_dirs = sidecar_exe_dir().into_iter().collect();
+        let target_triple = tauri::utils::platform::target_triple().ok();
+        Self::new(bundled_dirs, target_triple, None, env::var_os("PATH"))
+    }
+
+    /// Forgets the binaries found so far, so the next lookup searches again
+    pub fn clear_cache(&self) {
+        self.resolved.lock().unwrap().clear();
+    }
+
+    pub fn configured_path(&self) -> Option<PathBuf> {
+        self.configured.lock().unwrap().clone()
+    }
+
+    /// Sets (or clears) the user-configured FFmpeg binary and saves it
+    /// FFprobe is looked up next to the configured FFmpeg
+    pub fn set_configured_path(&self, path: Option<PathBuf>) -> Result<(), ClipforgeError> {
+        if let Some(path) = &path
+            && !path.is_file()
+        {
+            return Err(ClipforgeError::new(
+                ErrorKind::FileNotFound,
+                format!("FFmpeg binary not found at: {:?}", path),
+            )
+            .with_path(path));
+        }
+
+        if let Some(settings_path) = &self.settings_path {
+            if let Some(parent) = settings_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| ClipforgeError::io("Failed to create settings directory", parent, e))?;
+            }
+            let json = serde_json::to_string_pretty(&LocatorSettings { ffmpeg_path: path.clone() })
+                .map_err(|e| {
+                    ClipforgeError::new(ErrorKind::Internal, format!("Failed to serialize FFmpeg settings: {}", e))
+                })?;
+            fs::write(settings_path, json)
+                .map_err(|e| ClipforgeError::io("Failed to save FFmpeg settings to", settings_path, e))?;
+        }
+
+        *self.configured.lock().unwrap() = path;
+        self.clear_cache();
+        Ok(())
+    }
+
+    fn search(&self, tool: Tool) -> Option<ResolvedBinary> {
+        let exe_name = format!("{}{}", tool.name(), env::consts::EXE_SUFFIX);
+
+        // An override is the only candidate
+        if let Some(ffmpeg) = &self.override_ffmpeg {
+            let path = match tool {
+                Tool::Ffmpeg => ffmpeg.clone(),
+                Tool::Ffprobe => ffmpeg.with_file_name(&exe_name),
+            };
+            return path
+                .is_file()
+                .then_some(ResolvedBinary { path, source: BinarySource::Configured });
+        }
+
+        // 1. Bundled sidecar, with or without the target triple suffix
+        let mut sidecar_names = vec![exe_name.clone()];
+        if let Some(triple) = &self.target_triple {
+            sidecar_names.push(format!("{}-{}{}", tool.name(), triple, env::consts::EXE_SUFFIX));
+        }
+        let bundled = self
+            .bundled_dirs
+            .iter()
+            .flat_map(|dir| sidecar_names.iter().map(move |name| dir.join(name)))
+            .find(|path| path.is_file());
+        if let Some(path) = bundled {
+            return Some(ResolvedBinary { path, source: BinarySource::Bundled });
+        }
+
+        // 2. User-configured binary (FFprobe is expected alongside FFmpeg)
+        if let Some(configured) = self.configured_path() {
+            let path = match tool {
+                Tool::Ffmpeg => configured,
+                Tool::Ffprobe => configured.with_file_name(&exe_name),
+            };
+            if path.is_file() {
+                return Some(ResolvedBinary { path, source: BinarySource::Configured });
+            }
+        }
+
+        // 3. System binary on PATH
+        self.search_path
+            .as_ref()
+            .and_then(|search_path| {
+                env::split_paths(search_path)
+                    .map(|dir| dir.join(&exe_name))
+                    .find(|path| path.is_file())
+            })
+            .map(|path| ResolvedBinary { path, source: BinarySource::System })
+    }
+}
+
+impl ToolLocator for FfmpegLocator {
+    /// Finds a tool, caching the result until the configured path changes
+    fn locate(&self, tool: Tool) -> Result<ResolvedBinary, ClipforgeError> {
+        if let Some(resolved) = self.resolved.lock().unwrap().get(&tool)
+            && resolved.path.is_file()
+        {
+            return Ok(resolved.clone());
+        }
+
+        let resolved = self.search(tool).ok_or_else(|| {
+            ClipforgeError::new(
+                ErrorKind::FfmpegMissing,
+                format!("{} not found: no bundled sidecar, no configured path, and not on PATH", tool.name()),
+            )
+        })?;
+        log::info!("Using {} ({:?}) at: {:?}", tool.name(), resolved.source, resolved.path);
+        self.resolved.lock().unwrap().insert(tool, resolved.clone());
+        Ok(resolved)
+    }
+}
+
+/// The executable's directory, searched for sidecars only on macOS, the one platform whose
+/// bundle ships `externalBin` (see tauri.macos.conf.json). Elsewhere the executable may be
+/// installed to a shared directory such as /usr/bin, whose system FFmpeg isn't a sidecar.
+fn sidecar_exe_dir() -> Option<PathBuf> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+/// Extracts the version from `ffmpeg -version` output
+/// e.g. "ffmpeg version 7.1.1 Copyright (c) 2000-2025..." -> "7.1.1"
+fn parse_version(output: &str) -> Option<String> {
+    let first_line = output.lines().next()?;
+    let mut words = first_line.split_whitespace();
+    words.find(|word| *word == "version")?;
+    words.next().map(|version| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir()
+            .join("clipforge_locator_tests")
+            .join(format!("{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+        fs::write(&path, b"").unwrap();
+        path
+    }
+
+    #[test]
+    fn prefers_bundled_then_configured_then_path() {
+        let root = temp_dir("order");
+        let bundled = root.join("bundled");
+        let configured = root.join("configured");
+        let system = root.join("system");
+        for dir in [&bundled, &configured, &system] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        let system_ffmpeg = touch(&system, "ffmpeg");
+        let configured_ffmpeg = touch(&configured, "ffmpeg");
+        let search_path = env::join_paths([&system]).unwrap();
+
+        let locator = FfmpegLocator::new(vec![bundled.clone()], None, None, Some(search_path));
+        let resolved = locator.locate(Tool::Ffmpeg).unwrap();
+        assert_eq!(resolved, ResolvedBinary { path: system_ffmpeg, source: BinarySource::System });
+
+        locator.set_configured_path(Some(configured_ffmpeg.clone())).unwrap();
+        let resolved = locator.locate(Tool::Ffmpeg).unwrap();
+        assert_eq!(resolved, ResolvedBinary { path: configured_ffmpeg, source: BinarySource::Configured });
+
+        let bundled_ffmpeg = touch(&bundled, "ffmpeg");
+        locator.clear_cache();
+        let resolved = locator.locate(Tool::Ffmpeg).unwrap();
+        assert_eq!(resolved, ResolvedBinary { path: bundled_ffmpeg, source: BinarySource::Bundled });
+    }
+
+    #[test]
+    fn override_replaces_bundled_and_path() {
+        let root = temp_dir("override");
+        let bundled = root.join("bundled");
+        let chosen = root.join("chosen");
+        for dir in [&bundled, &chosen] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        touch(&bundled, "ffmpeg");
+        touch(&bundled, "ffprobe");
+        let chosen_ffmpeg = touch(&chosen, "ffmpeg");
+        let search_path = env::join_paths([&bundled]).unwrap();
+
+        let locator = FfmpegLocator::new(vec![bundled.clone()], None, None, Some(search_path))
+            .with_override(chosen_ffmpeg.clone())
+            .unwrap();
+        assert_eq!(
+            locator.locate(Tool::Ffmpeg).unwrap(),
+            ResolvedBinary { path: chosen_ffmpeg, source: BinarySource::Configured }
+        );
+        // No FFprobe beside the override, and the bundled one isn't used instead
+        assert_eq!(locator.locate(Tool::Ffprobe).unwrap_err().kind, ErrorKind::FfmpegMissing);
+
+        let missing = FfmpegLocator::new(vec![bundled], None, None, None).with_override(chosen.join("nope"));
+        assert_eq!(missing.err().map(|e| e.kind), Some(ErrorKind::FileNotFound));
+    }
+
+    #[test]
+    fn finds_sidecars_named_with_the_target_triple() {
+        let root = temp_dir("triple");
+        let sidecar = touch(&root, "ffprobe-x86_64-unknown-linux-gnu");
+        let locator = FfmpegLocator::new(
+            vec![root.join("missing"), root.clone()],
+            Some("x86_64-unknown-linux-gnu".to_string()),
+            None,
+            None,
+        );
+        assert_eq!(locator.locate(Tool::Ffprobe).unwrap().path, sidecar);
+        assert!(locator.locate(Tool::Ffmpeg).is_err());
+    }
+
+    #[test]
+    fn configured_path_is_saved_and_ffprobe_found_alongside() {
+        let root = temp_dir("settings");
+        let ffmpeg = touch(&root, "ffmpeg");
+        let ffprobe = touch(&root, "ffprobe");
+        let settings = root.join("config").join("ffmpeg.json");
+
+        let locator = FfmpegLocator::new(Vec::new(), None, Some(settings.clone()), None);
+        assert!(locator.set_configured_path(Some(root.join("nope"))).is_err());
+        locator.set_configured_path(Some(ffmpeg.clone())).unwrap();
+
+        let reloaded = FfmpegLocator::new(Vec::new(), None, Some(settings), None);
+        assert_eq!(reloaded.configured_path(), Some(ffmpeg));
+        assert_eq!(
+            reloaded.locate(Tool::Ffprobe).unwrap(),
+            ResolvedBinary { path: ffprobe, source: BinarySource::Configured }
+        );
+    }
+
+    #[test]
+    fn parses_version_output() {
+        assert_eq!(
+            parse_version("ffmpeg version 7.1.1 Copyright (c) 2000-2025 the FFmpeg developers\nbuilt with"),
+            Some("7.1.1".to_string())
+        );
+        assert_eq!(
+            parse_version("ffprobe version n6.1-3-gabc Copyright"),
+            Some("n6.1-3-gabc".to_string())
+        );
+        assert_eq!(parse_version(""), None);
+    }
+}
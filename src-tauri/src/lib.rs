@@ -1,16 +1,96 @@
 use ffmpeg_next as ffmpeg;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{Emitter, Manager};
-use tauri_plugin_shell::ShellExt;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+pub mod archive;
+pub mod autosave;
+pub mod error;
+pub mod export;
+pub mod ffmpeg_capabilities;
+pub mod ffmpeg_locator;
+pub mod fs_util;
+pub mod logging;
+pub mod media;
+pub mod media_import;
+pub mod project;
+pub mod relink;
+#[cfg(test)]
+mod test_support;
+
+use error::{ClipforgeError, ErrorKind};
+use ffmpeg_capabilities::CapabilityCache;
+use ffmpeg_locator::{FfmpegLocator, Tool, ToolLocator};
+
+/// What kind of source a media file is
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    #[default]
+    Video,
+    /// No video stream (cover art doesn't count); placed on the timeline as sound only
+    Audio,
+    /// A still picture, held on screen for the clip's duration
+    Image,
+}
+
+/// Fields added after the first release default when missing, so saved clips still load
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct VideoMetadata {
+    pub media_kind: MediaKind,
     pub filename: String,
     pub path: String,
     pub duration: f64,
-    pub resolution: String,
+    pub resolution: String, // "WxH", kept for older frontends
     pub codec: String,
+    /// Coded frame size in pixels (before rotation)
+    pub width: u32,
+    pub height: u32,
+    /// Average frame rate over the stream, 0 when unknown
+    pub avg_frame_rate: f64,
+    /// Base frame rate (lowest rate that represents all timestamps), 0 when unknown
+    pub real_frame_rate: f64,
+    pub is_vfr: bool,
+    /// Bits per second of the video stream, or of the whole file if the stream doesn't say
+    pub bit_rate: u64,
+    pub pix_fmt: Option<String>,
+    pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    /// Clockwise rotation (0, 90, 180, 270) needed to display the video upright
+    pub rotation: i32,
+    /// Pixel width / height, 1.0 for square pixels
+    pub sample_aspect_ratio: f64,
+    /// Container format name as reported by FFmpeg, e.g. "mov,mp4,m4a,3gp,3g2,mj2"
+    pub container: String,
+    pub file_size: u64,
+    /// Creation time tag from the file, if present (ISO 8601)
+    pub creation_time: Option<String>,
+    /// Audio streams in file order; empty when the file has no sound
+    pub audio_streams: Vec<AudioStream>,
+}
+
+/// An audio stream of a media file
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AudioStream {
+    /// Position among the file's audio streams (0 = first), as used by `TimelineClip::audio_stream`
+    pub index: usize,
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Layout name such as "stereo" or "5.1", None when FFmpeg doesn't know it
+    pub channel_layout: Option<String>,
+    pub bit_rate: u64,
+    /// Language tag (usually ISO 639-2, e.g. "eng"), if present
+    pub language: Option<String>,
+    /// Title tag, if present (e.g. "Director's commentary")
+    pub title: Option<String>,
+    /// Duration in seconds
+    pub duration: f64,
+    /// Whether the file marks this as the default audio stream
+    pub is_default: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,14 +102,76 @@ pub struct TimelineClip {
     pub trim_start: f64,
     pub trim_end: f64,
     pub duration: f64,
+    /// Placement of the clip when it is composited over Track 0
+    /// Ignored for Track 0 clips; overlays without one use the default PiP layout
+    #[serde(default)]
+    pub transform: Option<OverlayTransform>,
+    /// Which of the source's audio streams to use (0 = first), for multi-language files
+    /// Clips without one use the stream the file marks as default
+    #[serde(default)]
+    pub audio_stream: Option<usize>,
+}
+
+/// Corner (or center) of the output frame an overlay is positioned against
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayAnchor {
+    TopLeft,
+    TopRight,
+    #[default]
+    BottomLeft,
+    BottomRight,
+    Center,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Border drawn around an overlay
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlayBorder {
+    pub width: f64, // Fraction of the output width
+    pub color: String, // FFmpeg color name or hex, e.g. "white" or "#ff8800"
+}
+
+/// Layout of an overlay clip relative to the output frame
+/// All sizes are fractions of the output resolution so layouts survive resolution changes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OverlayTransform {
+    pub anchor: OverlayAnchor,
+    pub offset_x: f64, // Fraction of the output width, measured inward from the anchor
+    pub offset_y: f64, // Fraction of the output height, measured inward from the anchor
+    pub width: f64, // Fraction of the output width; height follows the source aspect ratio
+    pub opacity: f64, // 0.0 (invisible) to 1.0 (opaque)
+    pub corner_radius: f64, // Fraction of the overlay's shorter side, 0.5 gives a circle/pill
+    pub border: Option<OverlayBorder>,
+}
+
+impl Default for OverlayTransform {
+    /// Bottom-left picture-in-picture at a quarter of the frame width
+    fn default() -> Self {
+        Self {
+            anchor: OverlayAnchor::BottomLeft,
+            offset_x: 0.02,
+            offset_y: 0.03,
+            width: 0.25,
+            opacity: 1.0,
+            corner_radius: 0.0,
+            border: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExportRequest {
     pub clips: Vec<TimelineClip>,
     pub output_path: String,
     pub resolution: String, // "Source", "720p", "1080p", "1440p", or "4K"
     pub format: String, // "mp4", "webm", or "mov"
+    /// Overrides the format's default video encoder, e.g. "libopenh264" when libx264 is missing
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    /// Overrides the format's default audio encoder
+    #[serde(default)]
+    pub audio_codec: Option<String>,
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -38,80 +180,225 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-/// Extracts video metadata using FFmpeg Rust bindings
-/// Returns duration (in seconds), resolution (WxH), and codec info
-fn extract_video_metadata(file_path: &str) -> Result<VideoMetadata, String> {
-    // Open the file with FFmpeg
-    let input = ffmpeg::format::input(&file_path)
-        .map_err(|e| format!("Failed to open video file: {}", e))?;
-
-    // Get duration
-    let duration = input.duration() as f64 / ffmpeg::ffi::AV_TIME_BASE as f64;
-
-    // Find the video stream
-    let stream = input
-        .streams()
-        .best(ffmpeg::media::Type::Video)
-        .ok_or_else(|| "No video stream found in file".to_string())?;
-
-    // Get codec context from stream parameters
-    let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
-        .map_err(|e| format!("Failed to get codec context: {}", e))?;
-
-    // Get codec name before consuming codec
-    let codec_name = codec
-        .codec()
-        .map(|c| c.name().to_string())
-        .unwrap_or_else(|| "unknown".to_string());
-
-    // Get video decoder to access video properties (this consumes codec)
-    let video = codec
-        .decoder()
-        .video()
-        .map_err(|e| format!("Failed to get video decoder: {}", e))?;
-
-    // Get resolution
-    let width = video.width();
-    let height = video.height();
-
-    let file_name = PathBuf::from(file_path)
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-
-    Ok(VideoMetadata {
-        filename: file_name,
-        path: file_path.to_string(),
-        duration,
-        resolution: format!("{}x{}", width, height),
-        codec: codec_name,
-    })
-}
-
-/// Opens a file picker dialog for video files
+/// Opens a file picker dialog for video, audio and image files
 /// Returns VideoMetadata with file info and duration/resolution
 #[tauri::command]
-fn pick_video_file() -> Result<Option<VideoMetadata>, String> {
+fn pick_video_file(image_duration: Option<f64>) -> Result<Option<VideoMetadata>, ClipforgeError> {
     // Use rfd file dialog (blocking is fine for this operation)
-    let file_path = rfd::FileDialog::new()
-        .add_filter("Video Files", &["mp4", "mov", "webm", "mkv", "avi"])
-        .pick_file();
+    let file_path = media_file_dialog().pick_file();
 
     if let Some(path) = file_path {
         let path_str = path.to_string_lossy().to_string();
-        let metadata = extract_video_metadata(&path_str)?;
+        let metadata = probe_media(&path_str, image_duration)?;
         Ok(Some(metadata))
     } else {
         Ok(None)
     }
 }
 
-/// Extracts metadata from a video file at the given path
+/// Extracts metadata from a video, audio or image file at the given path
 /// Used for drag-and-drop file imports
 #[tauri::command]
-fn pick_video_file_by_path(path: String) -> Result<VideoMetadata, String> {
-    extract_video_metadata(&path)
+fn pick_video_file_by_path(path: String, image_duration: Option<f64>) -> Result<VideoMetadata, ClipforgeError> {
+    probe_media(&path, image_duration)
+}
+
+/// Probes a media file, telling missing files apart from ones FFmpeg can't read
+fn probe_media(path: &str, image_duration: Option<f64>) -> Result<VideoMetadata, ClipforgeError> {
+    if !Path::new(path).is_file() {
+        return Err(ClipforgeError::file_not_found(path));
+    }
+    media_import::probe(path, image_duration)
+        .map_err(|e| ClipforgeError::new(ErrorKind::UnsupportedMedia, e).with_path(path))
+}
+
+fn media_file_dialog() -> rfd::FileDialog {
+    use media_import::{AUDIO_EXTENSIONS, IMAGE_EXTENSIONS, VIDEO_EXTENSIONS};
+
+    let all_media = [VIDEO_EXTENSIONS, AUDIO_EXTENSIONS, IMAGE_EXTENSIONS].concat();
+    rfd::FileDialog::new()
+        .add_filter("Media", &all_media)
+        .add_filter("Video Files", VIDEO_EXTENSIONS)
+        .add_filter("Audio Files", AUDIO_EXTENSIONS)
+        .add_filter("Images", IMAGE_EXTENSIONS)
+}
+
+/// Opens a multi-select file picker, or a folder picker when `folder` is set
+/// Returns the chosen paths for `import_media_paths` (empty if cancelled)
+#[tauri::command]
+fn pick_media_paths(folder: Option<bool>) -> Vec<String> {
+    let paths = if folder.unwrap_or(false) {
+        rfd::FileDialog::new().pick_folder().map(|path| vec![path])
+    } else {
+        media_file_dialog().pick_files()
+    };
+    paths
+        .unwrap_or_default()
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Imports files and folders (searched recursively), probing files in parallel
+/// Emits `import_progress` as each file is probed; unsupported files are skipped with a reason
+#[tauri::command]
+async fn import_media_paths(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    image_duration: Option<f64>,
+) -> Result<media_import::ImportReport, ClipforgeError> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        media_import::import_paths(&paths, image_duration, |progress| {
+            let _ = app.emit("import_progress", &progress);
+        })
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Import failed: {}", e)))?;
+
+    log::info!(
+        "Imported {} files ({} failed, {} skipped)",
+        report.imported.len(),
+        report.errors.len(),
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// Saves the media library, timeline, export settings and markers to a project file
+#[tauri::command]
+fn save_project(path: String, project: project::Project) -> Result<(), ClipforgeError> {
+    project::save(&PathBuf::from(&path), &project)?;
+    log::info!("Saved project to {}", path);
+    Ok(())
+}
+
+/// A project opened by `load_project`, with the media whose files have gone missing
+#[derive(Debug, Serialize, Clone)]
+pub struct LoadedProject {
+    pub project: project::Project,
+    pub missing: Vec<relink::MissingMedia>,
+}
+
+/// Opens a project file, upgrading it if it was saved by an older version
+#[tauri::command]
+fn load_project(path: String) -> Result<LoadedProject, ClipforgeError> {
+    let project = project::load(&PathBuf::from(&path))?;
+    let missing = relink::find_missing(&project);
+    log::info!(
+        "Loaded project {} ({} media, {} missing, {} timeline clips)",
+        path,
+        project.media.len(),
+        missing.len(),
+        project.clips.len()
+    );
+    Ok(LoadedProject { project, missing })
+}
+
+/// Lists the project's media whose files no longer exist
+#[tauri::command]
+fn find_missing_media(project: project::Project) -> Vec<relink::MissingMedia> {
+    relink::find_missing(&project)
+}
+
+/// Points a media entry at a replacement file, checking that it matches the original
+#[tauri::command]
+fn relink_media(media: project::ProjectMedia, path: String) -> Result<project::ProjectMedia, ClipforgeError> {
+    let replacement = probe_media(&path, None)?;
+    relink::relink(&media, &path, |_| Ok(replacement.clone()))
+        .map_err(|e| ClipforgeError::new(ErrorKind::IncompatibleMedia, e).with_path(&path))
+}
+
+/// Searches a folder (recursively) for replacements of missing media, matched by filename
+/// and size and verified by duration
+#[tauri::command]
+async fn relink_media_folder(
+    media: Vec<project::ProjectMedia>,
+    root: String,
+) -> Result<relink::RelinkReport, ClipforgeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        relink::relink_folder(&media, &PathBuf::from(root), |path| media_import::probe(path, None))
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Relink failed: {}", e)))
+}
+
+/// Copies a project and its media into a folder or zip with relative paths, optionally
+/// keeping only the trimmed ranges the timeline uses (plus handles)
+#[tauri::command]
+async fn archive_project(
+    app: tauri::AppHandle,
+    project: project::Project,
+    options: archive::ArchiveOptions,
+) -> Result<archive::ArchiveResult, ClipforgeError> {
+    let plan = archive::plan_archive(&project, options.trim_media, options.handle_seconds);
+
+    // Locating FFmpeg and probing its capabilities runs FFmpeg, so it happens off the main
+    // thread along with the archiving
+    tauri::async_runtime::spawn_blocking(move || {
+        let (ffmpeg, video_encoder, audio_encoder) = {
+            let locator = app.state::<FfmpegLocator>();
+            let ffmpeg = locator.locate(Tool::Ffmpeg)?;
+            let (video, audio) = export::plan::default_encoders("mp4");
+            if plan.files.iter().any(|file| file.range.is_some()) {
+                // Trimmed media is re-encoded as H.264/AAC, or the closest encoders this FFmpeg has
+                let capabilities = app.state::<CapabilityCache>().get(&*locator, false)?;
+                let pick = |encoder: &'static str| {
+                    if capabilities.encoders.contains(encoder) {
+                        encoder
+                    } else {
+                        capabilities.fallback_for(encoder).unwrap_or(encoder)
+                    }
+                };
+                (ffmpeg, pick(video), pick(audio))
+            } else {
+                (ffmpeg, video, audio)
+            }
+        };
+
+        let trim = |file: &archive::ArchiveFile, output: &Path| {
+            let args = archive::trim_args(file, output, video_encoder, audio_encoder);
+            log::debug!("Trimming {} for archive: {:?}", file.source.display(), args);
+            ffmpeg
+                .run(&args, &format!("Failed to trim {}", file.source.display()))
+                .map(|_| ())
+                .map_err(|e| e.with_path(&file.source))
+        };
+        let archived = archive::write_archive(&plan, &options, trim, |path| probe_media(path, None))?;
+        log::info!(
+            "Archived project to {} ({} media files, {} bytes)",
+            archived.path, archived.media_files, archived.total_bytes
+        );
+        Ok(archived)
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Archive failed: {}", e)))?
+}
+
+/// Snapshots the project for crash recovery; unchanged projects are not written again
+/// Returns whether a snapshot was written
+#[tauri::command]
+fn autosave_project(
+    app: tauri::AppHandle,
+    project: project::Project,
+    project_path: Option<String>,
+) -> Result<bool, ClipforgeError> {
+    app.state::<autosave::Autosave>()
+        .save(&project, project_path)
+        .map_err(|e| ClipforgeError::new(ErrorKind::Io, e))
+}
+
+/// Newest autosave snapshot to offer on startup, if the last session crashed or the
+/// webview reloaded since it was taken
+#[tauri::command]
+fn check_recovery(app: tauri::AppHandle) -> Option<autosave::Snapshot> {
+    app.state::<autosave::Autosave>().recovery()
+}
+
+/// Declines recovery and deletes the snapshots
+#[tauri::command]
+fn discard_recovery(app: tauri::AppHandle) {
+    app.state::<autosave::Autosave>().discard_recovery();
 }
 
 /// Generate a filmstrip (vertical series of thumbnails) from a video file
@@ -122,8 +409,8 @@ fn generate_filmstrip(
     video_path: String,
     clip_id: String,
     frame_count: u32,
-) -> Result<String, String> {
-    println!(
+) -> Result<String, ClipforgeError> {
+    log::debug!(
         "Generating filmstrip for: {} (clip_id: {}, frames: {})",
         video_path, clip_id, frame_count
     );
@@ -133,73 +420,73 @@ fn generate_filmstrip(
     // Create persistent cache directory for filmstrips
     let cache_dir = env::temp_dir().join("clipforge_cache").join("filmstrips");
     fs::create_dir_all(&cache_dir)
-        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        .map_err(|e| ClipforgeError::io("Failed to create cache directory", &cache_dir, e))?;
 
-    // Generate filename for filmstrip
-    let filmstrip_filename = format!("{}_filmstrip.png", clip_id);
+    // Strips with a different frame count are separate files
+    let filmstrip_filename = format!("{}_{}f_filmstrip.png", clip_id, frame_count);
     let filmstrip_path = cache_dir.join(&filmstrip_filename);
 
     // Check if filmstrip already exists (caching)
     if filmstrip_path.exists() {
-        println!("Filmstrip already exists, returning cached version");
+        log::debug!("Filmstrip already exists, returning cached version");
         return Ok(filmstrip_path.to_string_lossy().to_string());
     }
 
-    // Get video metadata to calculate frame selection interval
-    let metadata = extract_video_metadata(&video_path)?;
-
-    // Build FFmpeg command for filmstrip generation
-    // Strategy: Extract frames at regular intervals, scale, and tile vertically
-    // For a 60fps 10s video (600 total frames) with 20 desired frames:
-    // We select every Nth frame to sample evenly across the video
-    let select_filter = format!(
-        "select='not(mod(n,{}))',scale=120:-2,tile=1x{}",
-        // Select every Nth frame (approximate, assuming 30fps baseline)
-        ((metadata.duration * 30.0) / frame_count as f64).max(1.0) as i32,
-        frame_count
-    );
-
-    // Use bundled FFmpeg sidecar
-    let output = tauri::async_runtime::block_on(async {
-        app.shell()
-            .sidecar("ffmpeg")
-            .map_err(|e| format!("Failed to create FFmpeg sidecar: {}", e))?
-            .args([
-                "-y", // Overwrite existing file
-                "-i",
-                &video_path, // Input file
-                "-vf",
-                &select_filter, // Filter: select frames, scale, tile vertically
-                "-frames",
-                "1", // Output 1 image (the tiled result)
-                filmstrip_path.to_string_lossy().as_ref(),
-            ])
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run FFmpeg: {}", e))
-    })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg filmstrip generation failed: {}", stderr));
+    let metadata = probe_media(&video_path, None)?;
+    if frame_count == 0 || metadata.duration <= 0.0 {
+        return Err(ClipforgeError::new(
+            ErrorKind::InvalidInput,
+            format!("Can't sample {} frames from {}", frame_count, video_path),
+        ));
     }
 
-    println!("Filmstrip generated successfully at: {:?}", filmstrip_path);
+    // Sample by timestamp rather than frame number so the strip spans the real duration
+    // whatever the frame rate: one frame from the middle of each of `frame_count` equal
+    // slices. The seek skips to the first slice's middle, then the fps filter takes a
+    // frame every slice length, scaled and tiled vertically.
+    let slice = metadata.duration / frame_count as f64;
+    let first_sample = format!("{:.6}", slice / 2.0);
+    let sample_filter = format!(
+        "fps={}/{:.6},scale=120:-2,tile=1x{}",
+        frame_count, metadata.duration, frame_count
+    );
+
+    app.state::<FfmpegLocator>().locate(Tool::Ffmpeg)?.run(
+        [
+            "-y", // Overwrite existing file
+            "-ss",
+            &first_sample,
+            "-i",
+            &video_path, // Input file
+            "-vf",
+            &sample_filter,
+            "-frames:v",
+            "1", // Output 1 image (the tiled result)
+            filmstrip_path.to_string_lossy().as_ref(),
+        ],
+        "FFmpeg filmstrip generation failed",
+    )?;
+
+    log::debug!("Filmstrip generated successfully at: {:?}", filmstrip_path);
     Ok(filmstrip_path.to_string_lossy().to_string())
 }
 
 /// Generate a thumbnail image from a video file at a specific timestamp
 /// Returns the base64-encoded PNG image data URL
 #[tauri::command]
-fn generate_thumbnail(app: tauri::AppHandle, video_path: String, timestamp: f64) -> Result<String, String> {
-    println!("Generating thumbnail for: {} at {}s", video_path, timestamp);
+fn generate_thumbnail(app: tauri::AppHandle, video_path: String, timestamp: f64) -> Result<String, ClipforgeError> {
+    log::debug!("Generating thumbnail for: {} at {}s", video_path, timestamp);
     use std::fs;
     use std::env;
 
+    if !Path::new(&video_path).is_file() {
+        return Err(ClipforgeError::file_not_found(&video_path));
+    }
+
     // Create temp directory for thumbnails if it doesn't exist
     let temp_dir = env::temp_dir().join("clipforge_thumbnails");
     fs::create_dir_all(&temp_dir)
-        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        .map_err(|e| ClipforgeError::io("Failed to create temp directory", &temp_dir, e))?;
 
     // Generate unique filename for thumbnail
     let thumbnail_filename = format!("thumb_{}_{}.png",
@@ -211,33 +498,23 @@ fn generate_thumbnail(app: tauri::AppHandle, video_path: String, timestamp: f64)
     );
     let thumbnail_path = temp_dir.join(thumbnail_filename);
 
-    // Use bundled FFmpeg sidecar to extract frame at timestamp
-    let output = tauri::async_runtime::block_on(async {
-        app.shell()
-            .sidecar("ffmpeg")
-            .map_err(|e| format!("Failed to create FFmpeg sidecar: {}", e))?
-            .args([
-                "-y", // Overwrite existing file
-                "-ss", &timestamp.to_string(), // Seek to timestamp
-                "-i", &video_path, // Input file
-                "-vframes", "1", // Extract 1 frame
-                "-vf", "scale=160:90", // Scale to thumbnail size (16:9 aspect ratio)
-                "-q:v", "2", // High quality
-                thumbnail_path.to_string_lossy().as_ref(),
-            ])
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run FFmpeg: {}", e))
-    })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg thumbnail generation failed: {}", stderr));
-    }
+    // Extract frame at timestamp
+    app.state::<FfmpegLocator>().locate(Tool::Ffmpeg)?.run(
+        [
+            "-y", // Overwrite existing file
+            "-ss", &timestamp.to_string(), // Seek to timestamp
+            "-i", &video_path, // Input file
+            "-vframes", "1", // Extract 1 frame
+            "-vf", "scale=160:90", // Scale to thumbnail size (16:9 aspect ratio)
+            "-q:v", "2", // High quality
+            thumbnail_path.to_string_lossy().as_ref(),
+        ],
+        "FFmpeg thumbnail generation failed",
+    )?;
 
     // Read the thumbnail file and convert to base64 data URL
     let thumbnail_data = fs::read(&thumbnail_path)
-        .map_err(|e| format!("Failed to read thumbnail file: {}", e))?;
+        .map_err(|e| ClipforgeError::io("Failed to read thumbnail file", &thumbnail_path, e))?;
 
     // Convert to base64
     let base64_data = base64_encode(&thumbnail_data);
@@ -274,265 +551,208 @@ fn base64_encode(data: &[u8]) -> String {
     result
 }
 
-/// Export video timeline to MP4 using FFmpeg with progress tracking
-/// For MVP: Simple implementation that handles single clips
-/// TODO: Add multi-clip concatenation and overlay support
-#[tauri::command]
-fn export_video(app: tauri::AppHandle, request: ExportRequest, clips_data: Vec<VideoMetadata>) -> Result<String, String> {
-    if request.clips.is_empty() {
-        return Err("No clips to export".to_string());
-    }
-
-    println!("Exporting {} clips", request.clips.len());
-
-    // Separate clips by track and sort by start time
-    let mut track0_clips: Vec<_> = request.clips.iter().filter(|c| c.track == 0).collect();
-    let mut track1_clips: Vec<_> = request.clips.iter().filter(|c| c.track == 1).collect();
-    track0_clips.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap());
-    track1_clips.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap());
-
-    if track0_clips.is_empty() {
-        return Err("No clips on main track (Track 0) to export".to_string());
-    }
+/// Prepares an export with the app's FFmpeg locator and capability cache
+fn prepare_export(
+    app: &tauri::AppHandle,
+    request: &ExportRequest,
+    clips_data: &[VideoMetadata],
+) -> Result<(export::ExportPlan, PathBuf), ClipforgeError> {
+    export::prepare_export(
+        request,
+        clips_data,
+        &*app.state::<FfmpegLocator>(),
+        &app.state::<CapabilityCache>(),
+    )
+}
 
-    // Calculate total expected duration for progress tracking
-    let expected_duration: f64 = track0_clips.iter()
-        .map(|c| c.trim_end - c.trim_start)
-        .sum();
-
-    // Determine target resolution
-    let (target_width, target_height) = match request.resolution.as_str() {
-        "720p" => (1280, 720),
-        "1080p" => (1920, 1080),
-        "1440p" => (2560, 1440),
-        "4K" => (3840, 2160),
-        _ => (1920, 1080), // Default to 1080p
+/// Starts a prepared export, forwarding its progress and outcome as events
+/// FFmpeg's log output is written to `<app log dir>/exports/<job_id>.log`
+fn start_app_export(
+    app: &tauri::AppHandle,
+    reservation: export::ExportReservation,
+    ffmpeg_path: PathBuf,
+    plan: export::ExportPlan,
+    output_path: String,
+    on_finish: impl FnOnce(export::ExportOutcome) + Send + 'static,
+) -> Result<(), ClipforgeError> {
+    let job = export::ExportJob {
+        ffmpeg_path,
+        plan,
+        output_path,
+        log_dir: app.path().app_log_dir().ok().map(|dir| dir.join("exports")),
     };
+    export::start_export(reservation, job, app.clone(), on_finish)
+}
 
-    // Build FFmpeg command arguments
-    let mut args: Vec<String> = vec![
-        "-y".to_string(),
-        "-progress".to_string(),
-        "pipe:2".to_string(),
-    ];
-
-    // Add all input files with trim parameters
-    let mut input_index = 0;
-    let mut filter_complex = String::new();
-
-    // Add Track 0 inputs and build trim/scale filters
-    for (idx, clip) in track0_clips.iter().enumerate() {
-        let source_clip = clips_data.iter()
-            .find(|c| c.path == clip.clip_id)
-            .ok_or_else(|| format!("Source clip not found: {}", clip.clip_id))?;
-
-        // Add input with seek and duration for faster processing
-        args.push("-ss".to_string());
-        args.push(clip.trim_start.to_string());
-        args.push("-t".to_string());
-        args.push((clip.trim_end - clip.trim_start).to_string());
-        args.push("-i".to_string());
-        args.push(source_clip.path.clone());
-
-        // Build filter: scale to target resolution, set SAR, format
-        filter_complex.push_str(&format!(
-            "[{}:v]scale={}:{}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30[v{}]; ",
-            input_index, target_width, target_height, target_width, target_height, idx
-        ));
-        input_index += 1;
-    }
-
-    // Concatenate all Track 0 clips
-    let concat_inputs: String = (0..track0_clips.len())
-        .map(|i| format!("[v{}]", i))
-        .collect::<Vec<_>>()
-        .join("");
-
-    filter_complex.push_str(&format!(
-        "{}concat=n={}:v=1:a=0[outv]",
-        concat_inputs,
-        track0_clips.len()
-    ));
-
-    // If Track 1 has clips, add overlay logic (bottom-left PiP)
-    if !track1_clips.is_empty() {
-        // For now, overlay the first Track 1 clip as PiP in bottom-left
-        let overlay_clip = track1_clips[0];
-        let overlay_source = clips_data.iter()
-            .find(|c| c.path == overlay_clip.clip_id)
-            .ok_or_else(|| format!("Overlay clip not found: {}", overlay_clip.clip_id))?;
-
-        // Add overlay input
-        args.push("-ss".to_string());
-        args.push(overlay_clip.trim_start.to_string());
-        args.push("-t".to_string());
-        args.push((overlay_clip.trim_end - overlay_clip.trim_start).to_string());
-        args.push("-i".to_string());
-        args.push(overlay_source.path.clone());
-
-        // Scale overlay to 320x240 and overlay in bottom-left corner with 20px margin
-        filter_complex.push_str(&format!(
-            "; [{}:v]scale=320:240[overlay]; [outv][overlay]overlay=20:H-h-20[outv]",
-            input_index
-        ));
+/// Forwards export events to the frontend
+impl export::ExportSink for tauri::AppHandle {
+    fn send(&self, event: export::ExportEvent) {
+        let name = event.name();
+        let _ = match event {
+            export::ExportEvent::Progress(progress) => self.emit(name, progress),
+            export::ExportEvent::Completed(completed) => self.emit(name, completed),
+            export::ExportEvent::Failed(failed) => self.emit(name, failed),
+            export::ExportEvent::Cancelled(cancelled) => self.emit(name, cancelled),
+        };
     }
+}
 
-    // Add filter_complex argument
-    args.push("-filter_complex".to_string());
-    args.push(filter_complex);
-
-    // Map the output video
-    args.push("-map".to_string());
-    args.push("[outv]".to_string());
-
-    // For audio, use the first input's audio track
-    args.push("-map".to_string());
-    args.push("0:a?".to_string());
-
-    // Output codec settings
-    match request.format.as_str() {
-        "webm" => {
-            args.extend_from_slice(&[
-                "-c:v".to_string(), "libvpx-vp9".to_string(),
-                "-crf".to_string(), "30".to_string(),
-                "-b:v".to_string(), "0".to_string(),
-                "-c:a".to_string(), "libopus".to_string(),
-                "-b:a".to_string(), "128k".to_string(),
-            ]);
-        }
-        "mov" => {
-            args.extend_from_slice(&[
-                "-c:v".to_string(), "libx264".to_string(),
-                "-preset".to_string(), "medium".to_string(),
-                "-crf".to_string(), "23".to_string(),
-                "-c:a".to_string(), "aac".to_string(),
-                "-b:a".to_string(), "192k".to_string(),
-            ]);
-        }
-        _ => {
-            args.extend_from_slice(&[
-                "-c:v".to_string(), "libx264".to_string(),
-                "-preset".to_string(), "medium".to_string(),
-                "-crf".to_string(), "23".to_string(),
-                "-c:a".to_string(), "aac".to_string(),
-                "-b:a".to_string(), "192k".to_string(),
-            ]);
-        }
+/// Export video timeline using FFmpeg with progress tracking
+/// Track 0 clips are concatenated (with gaps as black/silence) to form the base video and audio;
+/// clips on every higher track are composited on top, higher tracks stacking over lower ones
+/// The caller picks `job_id`, so it can match events to this export before the command
+/// returns; the render runs in the background and reports `export_progress`, then one of
+/// `export_complete`, `export_failed` or `export_cancelled`
+#[tauri::command]
+async fn export_video(
+    app: tauri::AppHandle,
+    job_id: String,
+    request: ExportRequest,
+    clips_data: Vec<VideoMetadata>,
+) -> Result<(), ClipforgeError> {
+    // The ID also names the export's log file
+    if job_id.is_empty() || !job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(ClipforgeError::new(ErrorKind::InvalidInput, format!("Invalid export job ID: {}", job_id)));
     }
 
-    args.push(request.output_path.clone());
-
-    println!("Running FFmpeg with args: {:?}", args);
+    // Claim the ID first so it can't be used twice and the export can be cancelled while
+    // it is prepared
+    let reservation = app.state::<export::ExportJobs>().reserve(job_id)?;
 
-    // Use bundled FFmpeg sidecar with real-time progress tracking
-    use std::process::{Command, Stdio};
-    use std::io::{BufRead, BufReader};
-    use std::time::Instant;
+    // Preparing probes every source and FFmpeg, so it runs on a blocking thread too; the
+    // render itself runs in the background and reports progress and the outcome as events
+    tauri::async_runtime::spawn_blocking(move || {
+        let (plan, ffmpeg_path) = prepare_export(&app, &request, &clips_data)?;
+        start_app_export(&app, reservation, ffmpeg_path, plan, request.output_path, |_| {})
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Export failed: {}", e)))?
+}
 
-    // Emit initial progress
-    let _ = app.emit("export_progress", 0u32);
+/// Cancel a running export: kills FFmpeg, deletes the partial output file
+/// and emits `export_cancelled`
+/// An export still being prepared is cancelled before FFmpeg starts
+#[tauri::command]
+fn cancel_export(jobs: tauri::State<export::ExportJobs>, job_id: String) -> Result<(), ClipforgeError> {
+    jobs.cancel(&job_id)
+}
 
-    // Resolve the FFmpeg sidecar path
-    // Use Tauri's target_triple for consistent naming
-    let target_triple = tauri::utils::platform::target_triple()
-        .map_err(|e| format!("Failed to get target triple: {}", e))?;
+/// Starts queued exports while the render queue has free slots
+/// Each job's status change is emitted as an `export_queue_status` event
+/// Preparing a job probes its sources and FFmpeg, so this runs on a blocking thread and
+/// returns at once; callers include app setup, which would otherwise hold up the window
+fn pump_render_queue(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || start_queued_exports(&app));
+}
 
-    let binary_name = if cfg!(target_os = "windows") {
-        format!("ffmpeg-{}.exe", target_triple)
-    } else {
-        format!("ffmpeg-{}", target_triple)
-    };
+fn start_queued_exports(app: &tauri::AppHandle) {
+    let queue = app.state::<export::RenderQueue>();
+    for job in queue.take_startable() {
+        let _ = app.emit("export_queue_status", &job);
+
+        // The job is registered while it is prepared so cancel_export can stop it
+        let started = app.state::<export::ExportJobs>().reserve(job.id.clone()).and_then(|reservation| {
+            let (plan, ffmpeg_path) = prepare_export(app, &job.request, &job.clips_data)?;
+            let app_handle = app.clone();
+            let job_id = job.id.clone();
+            start_app_export(app, reservation, ffmpeg_path, plan, job.request.output_path.clone(), move |outcome| {
+                let (status, error) = match outcome {
+                    export::ExportOutcome::Completed => (export::QueueJobStatus::Completed, None),
+                    export::ExportOutcome::Failed(failure) => (export::QueueJobStatus::Failed, Some(failure.message)),
+                    export::ExportOutcome::Cancelled => (export::QueueJobStatus::Cancelled, None),
+                };
+                finish_queued_export(&app_handle, &job_id, status, error);
+            })
+        });
 
-    let sidecar_path = if cfg!(dev) {
-        // Development: binaries are in src-tauri/binaries/
-        // current_dir() is already at project root or src-tauri, so check both
-        let current = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current dir: {}", e))?;
-
-        // Try src-tauri/binaries first (if we're at project root)
-        let path_from_root = current.join("src-tauri").join("binaries").join(&binary_name);
-        if path_from_root.exists() {
-            println!("Dev mode: Using FFmpeg at: {:?}", path_from_root);
-            path_from_root
-        } else {
-            // Try binaries/ (if we're already in src-tauri/)
-            let path_from_tauri = current.join("binaries").join(&binary_name);
-            println!("Dev mode: Using FFmpeg at: {:?}", path_from_tauri);
-            path_from_tauri
+        if let Err(error) = started {
+            finish_queued_export(app, &job.id, export::QueueJobStatus::Failed, Some(error.message));
         }
-    } else {
-        // Production: use bundled sidecar from resources
-        let resource_dir = app.path().resource_dir()
-            .map_err(|e| format!("Failed to get resource dir: {}", e))?;
-
-        let prod_path = resource_dir.join(&binary_name);
-        println!("Production mode: Using FFmpeg at: {:?}", prod_path);
-        prod_path
-    };
-
-    if !sidecar_path.exists() {
-        return Err(format!("FFmpeg binary not found at: {:?}", sidecar_path));
     }
+}
 
-    // Convert args to string references for Command
-    let args_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-
-    // Spawn FFmpeg process with piped stderr for progress tracking
-    let mut child = Command::new(sidecar_path)
-        .args(&args_refs)
-        .stderr(Stdio::piped())
-        .stdout(Stdio::null())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn FFmpeg: {}", e))?;
-
-    // Stream stderr for progress updates
-    if let Some(stderr) = child.stderr.take() {
-        let reader = BufReader::new(stderr);
-        let mut last_emit = Instant::now();
-        let app_clone = app.clone();
-
-        std::thread::spawn(move || {
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    // Parse FFmpeg progress output: "out_time_ms=1234567"
-                    if line.starts_with("out_time_ms=") {
-                        if let Some(time_str) = line.strip_prefix("out_time_ms=") {
-                            if let Ok(time_us) = time_str.parse::<i64>() {
-                                let current_time = time_us as f64 / 1_000_000.0;
-                                let progress_percent = ((current_time / expected_duration) * 100.0).min(99.0);
-
-                                // Emit progress event every 300ms
-                                if last_emit.elapsed().as_millis() >= 300 {
-                                    let _ = app_clone.emit("export_progress", progress_percent as u32);
-                                    last_emit = Instant::now();
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        });
+/// Records a queued export's outcome and starts the next pending job
+fn finish_queued_export(
+    app: &tauri::AppHandle,
+    job_id: &str,
+    status: export::QueueJobStatus,
+    error: Option<String>,
+) {
+    if let Some(job) = app.state::<export::RenderQueue>().finish(job_id, status, error) {
+        let _ = app.emit("export_queue_status", &job);
     }
+    pump_render_queue(app);
+}
 
-    // Wait for process to complete
-    let status = child.wait()
-        .map_err(|e| format!("Failed to wait for FFmpeg: {}", e))?;
+/// Add an export to the render queue
+/// Jobs render in the background in queue order; running jobs can be stopped with cancel_export
+#[tauri::command]
+async fn enqueue_export(
+    app: tauri::AppHandle,
+    request: ExportRequest,
+    clips_data: Vec<VideoMetadata>,
+) -> Result<export::QueuedExport, ClipforgeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Reject exports that can never render before they wait in the queue
+        prepare_export(&app, &request, &clips_data)?;
+
+        let job = app.state::<export::RenderQueue>().enqueue(export::new_job_id(), request, clips_data);
+        let _ = app.emit("export_queue_status", &job);
+        pump_render_queue(&app);
+        Ok(job)
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Enqueue failed: {}", e)))?
+}
 
-    if !status.success() {
-        return Err(format!("FFmpeg export failed with exit code: {:?}", status.code()));
-    }
+/// List every job in the render queue, in order
+#[tauri::command]
+fn list_export_queue(queue: tauri::State<export::RenderQueue>) -> Vec<export::QueuedExport> {
+    queue.list()
+}
 
-    // Emit 100% completion
-    let _ = app.emit("export_progress", 100u32);
+/// Move a job to a new position in the render queue
+#[tauri::command]
+fn reorder_export_job(
+    queue: tauri::State<export::RenderQueue>,
+    job_id: String,
+    position: usize,
+) -> Result<(), ClipforgeError> {
+    queue
+        .reorder(&job_id, position)
+        .map_err(|e| ClipforgeError::new(ErrorKind::InvalidInput, e))
+}
 
-    println!("Export completed successfully");
+/// Remove a job that is not currently rendering from the render queue
+#[tauri::command]
+fn remove_export_job(
+    queue: tauri::State<export::RenderQueue>,
+    job_id: String,
+) -> Result<(), ClipforgeError> {
+    queue
+        .remove(&job_id)
+        .map(|_| ())
+        .map_err(|e| ClipforgeError::new(ErrorKind::InvalidInput, e))
+}
 
-    Ok(request.output_path)
+/// Set how many queued exports may render at the same time
+#[tauri::command]
+fn set_export_concurrency(
+    app: tauri::AppHandle,
+    queue: tauri::State<export::RenderQueue>,
+    concurrency: usize,
+) -> Result<(), ClipforgeError> {
+    queue
+        .set_concurrency(concurrency)
+        .map_err(|e| ClipforgeError::new(ErrorKind::InvalidInput, e))?;
+    pump_render_queue(&app);
+    Ok(())
 }
 
 /// Open the recorder window (400x500, always-on-top)
 #[tauri::command]
-fn open_recorder_window(app: tauri::AppHandle) -> Result<(), String> {
+fn open_recorder_window(app: tauri::AppHandle) -> Result<(), ClipforgeError> {
     use tauri::WebviewWindowBuilder;
     use tauri::WebviewUrl;
     use tauri::Manager;
@@ -540,8 +760,8 @@ fn open_recorder_window(app: tauri::AppHandle) -> Result<(), String> {
     // Check if recorder window already exists
     if let Some(window) = app.get_webview_window("recorder") {
         // Window exists - bring it to front and focus it
-        window.show().map_err(|e| format!("Failed to show recorder window: {}", e))?;
-        window.set_focus().map_err(|e| format!("Failed to focus recorder window: {}", e))?;
+        window.show().map_err(|e| window_error("show", e))?;
+        window.set_focus().map_err(|e| window_error("focus", e))?;
         return Ok(());
     }
 
@@ -552,59 +772,68 @@ fn open_recorder_window(app: tauri::AppHandle) -> Result<(), String> {
         .resizable(false)
         .always_on_top(true)
         .build()
-        .map_err(|e| format!("Failed to create recorder window: {}", e))?;
+        .map_err(|e| window_error("create", e))?;
 
     Ok(())
 }
 
 /// Close the recorder window
 #[tauri::command]
-fn close_recorder_window(app: tauri::AppHandle) -> Result<(), String> {
+fn close_recorder_window(app: tauri::AppHandle) -> Result<(), ClipforgeError> {
     if let Some(window) = app.get_webview_window("recorder") {
         window
             .close()
-            .map_err(|e| format!("Failed to close recorder window: {}", e))?;
+            .map_err(|e| window_error("close", e))?;
     }
     Ok(())
 }
 
+fn window_error(action: &str, error: tauri::Error) -> ClipforgeError {
+    ClipforgeError::new(ErrorKind::Internal, format!("Failed to {} recorder window: {}", action, error))
+}
+
+/// `ClipForge` folder in the user's Documents, where recordings are saved
+fn recordings_dir() -> Result<PathBuf, ClipforgeError> {
+    dirs::document_dir()
+        .map(|dir| dir.join("ClipForge"))
+        .ok_or_else(|| ClipforgeError::new(ErrorKind::FileNotFound, "Could not find Documents directory"))
+}
+
 /// Save recording blob to disk
 /// Returns the full file path of the saved recording
 #[tauri::command]
-fn save_recording(blob: Vec<u8>, filename: String) -> Result<String, String> {
+fn save_recording(blob: Vec<u8>, filename: String) -> Result<String, ClipforgeError> {
     use std::fs;
 
     // Create ClipForge directory in user's Documents folder
-    let home_dir = dirs::document_dir()
-        .ok_or_else(|| "Could not find Documents directory".to_string())?;
-
-    let clipforge_dir = home_dir.join("ClipForge");
+    let clipforge_dir = recordings_dir()?;
     fs::create_dir_all(&clipforge_dir)
-        .map_err(|e| format!("Failed to create ClipForge directory: {}", e))?;
+        .map_err(|e| ClipforgeError::io("Failed to create ClipForge directory", &clipforge_dir, e))?;
 
     // Build output path
     let output_path = clipforge_dir.join(&filename);
 
     // Write blob to file
     fs::write(&output_path, blob)
-        .map_err(|e| format!("Failed to write recording file: {}", e))?;
+        .map_err(|e| ClipforgeError::io("Failed to write recording file", &output_path, e))?;
 
     Ok(output_path.to_string_lossy().to_string())
 }
 
-/// Convert WebM recording to MP4 using FFmpeg sidecar
+/// Convert WebM recording to MP4 using FFmpeg
 /// Returns the full file path of the MP4 file
 #[tauri::command]
-fn convert_webm_to_mp4(app: tauri::AppHandle, input_path: String, output_filename: String) -> Result<String, String> {
+fn convert_webm_to_mp4(app: tauri::AppHandle, input_path: String, output_filename: String) -> Result<String, ClipforgeError> {
     use std::fs;
     use std::path::PathBuf;
 
     let input_path_buf = PathBuf::from(&input_path);
+    if !input_path_buf.is_file() {
+        return Err(ClipforgeError::file_not_found(&input_path_buf));
+    }
 
     // Build output path in ClipForge directory
-    let home_dir = dirs::document_dir()
-        .ok_or_else(|| "Could not find Documents directory".to_string())?;
-    let clipforge_dir = home_dir.join("ClipForge");
+    let clipforge_dir = recordings_dir()?;
     let output_path = clipforge_dir.join(&output_filename);
 
     // Build FFmpeg command: convert WebM to MP4 with H.264 codec
@@ -617,55 +846,151 @@ fn convert_webm_to_mp4(app: tauri::AppHandle, input_path: String, output_filenam
         "-b:a", "192k",         // Audio bitrate
         "-movflags", "+faststart", // Enable streaming
         "-y",                   // Overwrite output file
-        output_path.to_str().ok_or("Invalid output path")?,
+        output_path
+            .to_str()
+            .ok_or_else(|| ClipforgeError::new(ErrorKind::InvalidInput, "Invalid output path"))?,
     ];
 
-    // Execute FFmpeg sidecar
-    let output = tauri::async_runtime::block_on(async {
-        app.shell()
-            .sidecar("ffmpeg")
-            .map_err(|e| format!("Failed to get ffmpeg sidecar: {}", e))?
-            .args(args)
-            .output()
-            .await
-            .map_err(|e| format!("Failed to execute ffmpeg: {}", e))
-    })?;
-
-    // Check if conversion succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg conversion failed: {}", stderr));
-    }
+    // Execute FFmpeg; fails if the conversion did not succeed
+    app.state::<FfmpegLocator>()
+        .locate(Tool::Ffmpeg)?
+        .run(args, "FFmpeg conversion failed")?;
 
     // Delete temporary WebM file
     if let Err(e) = fs::remove_file(&input_path_buf) {
-        eprintln!("Warning: Failed to delete temp WebM file: {}", e);
+        log::warn!("Failed to delete temp WebM file: {}", e);
     }
 
     Ok(output_path.to_string_lossy().to_string())
 }
 
+/// Report which FFmpeg and FFprobe binaries are in use, where they came from and their versions
+#[tauri::command]
+fn get_ffmpeg_info(locator: tauri::State<FfmpegLocator>) -> Result<Vec<ffmpeg_locator::ToolInfo>, ClipforgeError> {
+    Ok(vec![locator.info(Tool::Ffmpeg)?, locator.info(Tool::Ffprobe)?])
+}
+
+/// Set the FFmpeg binary to use when no bundled sidecar is available
+/// Pass null to clear it and fall back to FFmpeg on PATH
+#[tauri::command]
+fn set_ffmpeg_path(
+    locator: tauri::State<FfmpegLocator>,
+    path: Option<String>,
+) -> Result<ffmpeg_locator::ToolInfo, ClipforgeError> {
+    locator.set_configured_path(path.map(PathBuf::from))?;
+    locator.info(Tool::Ffmpeg)
+}
+
+/// Report the encoders, muxers and filters available in the FFmpeg in use
+/// Results are cached; pass refresh to probe FFmpeg again
+/// Probing runs FFmpeg three times, so it happens on a blocking thread
+#[tauri::command]
+async fn get_ffmpeg_capabilities(
+    app: tauri::AppHandle,
+    refresh: Option<bool>,
+) -> Result<ffmpeg_capabilities::FfmpegCapabilities, ClipforgeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let locator = app.state::<FfmpegLocator>();
+        app.state::<CapabilityCache>().get(&*locator, refresh.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("FFmpeg capability probe failed: {}", e)))?
+}
+
+/// Last lines of the app log, oldest first, for attaching to bug reports
+#[tauri::command]
+fn get_recent_logs(app: tauri::AppHandle, lines: Option<usize>) -> Result<Vec<String>, ClipforgeError> {
+    logging::recent_lines(&app_log_dir(&app)?, lines.unwrap_or(200))
+}
+
+/// Opens the app log folder in the system file manager
+#[tauri::command]
+fn open_log_folder(app: tauri::AppHandle) -> Result<(), ClipforgeError> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let log_dir = app_log_dir(&app)?;
+    std::fs::create_dir_all(&log_dir)
+        .map_err(|e| ClipforgeError::io("Failed to create log folder", &log_dir, e))?;
+    app.opener()
+        .open_path(log_dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| {
+            ClipforgeError::new(ErrorKind::Internal, format!("Failed to open {}: {}", log_dir.display(), e))
+                .with_path(&log_dir)
+        })
+}
+
+fn app_log_dir(app: &tauri::AppHandle) -> Result<PathBuf, ClipforgeError> {
+    app.path()
+        .app_log_dir()
+        .map_err(|e| ClipforgeError::new(ErrorKind::FileNotFound, format!("Could not find the app log folder: {}", e)))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize FFmpeg
     ffmpeg::init().expect("Failed to initialize FFmpeg");
 
     tauri::Builder::default()
+        .plugin(logging::plugin())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .manage(export::ExportJobs::default())
+        .manage(CapabilityCache::default())
+        .setup(|app| {
+            // Locate FFmpeg before anything (including the render queue) needs it
+            app.manage(FfmpegLocator::for_app(app.handle()));
+
+            // Restore the render queue and resume renders left pending when the app last quit
+            let queue_path = app.path().app_data_dir()?.join("export_queue.json");
+            app.manage(export::RenderQueue::load(queue_path));
+            pump_render_queue(app.handle());
+
+            // Take the session lock; a lock left by the last session means it crashed
+            let autosave_dir = app.path().app_data_dir()?.join("autosave");
+            app.manage(autosave::Autosave::start(autosave_dir));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             pick_video_file,
             pick_video_file_by_path,
+            pick_media_paths,
+            import_media_paths,
+            save_project,
+            load_project,
+            find_missing_media,
+            relink_media,
+            relink_media_folder,
+            archive_project,
             generate_thumbnail,
             generate_filmstrip,
             export_video,
+            cancel_export,
+            enqueue_export,
+            list_export_queue,
+            reorder_export_job,
+            remove_export_job,
+            set_export_concurrency,
             open_recorder_window,
             close_recorder_window,
             save_recording,
-            convert_webm_to_mp4
+            convert_webm_to_mp4,
+            get_ffmpeg_info,
+            set_ffmpeg_path,
+            get_ffmpeg_capabilities,
+            autosave_project,
+            check_recovery,
+            discard_recovery,
+            get_recent_logs,
+            open_log_folder
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Clean exit: nothing to recover next time
+                app.state::<autosave::Autosave>().finish();
+            }
+        });
 }
@@ -0,0 +1,114 @@
+//! App log: levelled, timestamped lines written to stdout and to `clipforge.log` in the
+//! app log directory
+//!
+//! A log that has grown past `MAX_FILE_SIZE` is renamed to `clipforge_<date>.log` at
+//! startup and a fresh one started; only the newest `KEPT_FILES` are kept.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tauri::Runtime;
+use tauri::plugin::TauriPlugin;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
+
+use crate::error::ClipforgeError;
+
+/// Log file name without the `.log` extension
+const LOG_FILE_NAME: &str = "clipforge";
+
+const MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+
+/// Log files kept, counting the current one
+const KEPT_FILES: usize = 5;
+
+/// Log plugin to register before the others so their setup is logged too
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    // Debug builds also log per-command detail such as FFmpeg arguments
+    let app_level = if cfg!(debug_assertions) {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Info
+    };
+
+    tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir {
+                file_name: Some(LOG_FILE_NAME.to_string()),
+            }),
+        ])
+        .level(log::LevelFilter::Info)
+        .level_for(env!("CARGO_CRATE_NAME"), app_level)
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_FILES))
+        .timezone_strategy(TimezoneStrategy::UseLocal)
+        .build()
+}
+
+/// Last `max_lines` lines logged, oldest first, continuing into rotated logs when the
+/// current one is shorter
+pub fn recent_lines(log_dir: &Path, max_lines: usize) -> Result<Vec<String>, ClipforgeError> {
+    let mut lines = Vec::new();
+    for path in log_files(log_dir)?.iter().rev() {
+        if lines.len() >= max_lines {
+            break;
+        }
+        let contents = fs::read(path).map_err(|e| ClipforgeError::io("Failed to read log", path, e))?;
+        let contents = String::from_utf8_lossy(&contents);
+        let file_lines: Vec<&str> = contents.lines().collect();
+        let start = file_lines.len().saturating_sub(max_lines - lines.len());
+        lines.splice(0..0, file_lines[start..].iter().map(|line| line.to_string()));
+    }
+    Ok(lines)
+}
+
+/// Log files oldest first: rotated logs in date order, then the current one
+fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>, ClipforgeError> {
+    let entries = match fs::read_dir(log_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ClipforgeError::io("Failed to read log folder", log_dir, e)),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(LOG_FILE_NAME)?.strip_prefix('_')?.strip_suffix(".log"))
+                .is_some()
+        })
+        .collect();
+    // Rotated names end in a sortable date, so name order is age order
+    files.sort();
+
+    let current = log_dir.join(format!("{}.log", LOG_FILE_NAME));
+    if current.is_file() {
+        files.push(current);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn reads_recent_lines_across_rotated_logs() {
+        let dir = temp_dir("logging");
+        fs::write(dir.join("clipforge_2026-01-01_09-00-00.log"), "a1\na2\n").unwrap();
+        fs::write(dir.join("clipforge_2026-01-02_09-00-00.log"), "b1\nb2\n").unwrap();
+        fs::write(dir.join("clipforge.log"), "c1\nc2\n").unwrap();
+        fs::write(dir.join("other.log"), "x\n").unwrap();
+
+        assert_eq!(recent_lines(&dir, 1).unwrap(), ["c2"]);
+        assert_eq!(recent_lines(&dir, 3).unwrap(), ["b2", "c1", "c2"]);
+        assert_eq!(recent_lines(&dir, 10).unwrap(), ["a1", "a2", "b1", "b2", "c1", "c2"]);
+        assert!(recent_lines(&dir.join("missing"), 10).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+//! Probes media files with the linked FFmpeg libraries
+
+use std::fs;
+use std::path::PathBuf;
+
+use ffmpeg_next as ffmpeg;
+
+use crate::{AudioStream, MediaKind, VideoMetadata};
+
+/// Average and real frame rates further apart than this (relative) mark a stream as variable frame rate
+const VFR_TOLERANCE: f64 = 0.01;
+
+/// Length given to still images when they are imported
+pub const DEFAULT_IMAGE_DURATION: f64 = 5.0;
+
+/// Reads duration, dimensions, frame rate, color, container and audio stream details from a media file
+/// Audio-only files and still images are accepted too (see `MediaKind`)
+pub fn extract_video_metadata(file_path: &str) -> Result<VideoMetadata, String> {
+    // Open the file with FFmpeg
+    let input = ffmpeg::format::input(&file_path)
+        .map_err(|e| format!("Failed to open media file: {}", e))?;
+
+    // Get duration
+    let duration = input.duration() as f64 / ffmpeg::ffi::AV_TIME_BASE as f64;
+
+    let file_name = PathBuf::from(file_path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let container = input.format().name().to_string();
+
+    let mut metadata = VideoMetadata {
+        filename: file_name,
+        path: file_path.to_string(),
+        duration,
+        container: container.clone(),
+        file_size: fs::metadata(file_path).map(|m| m.len()).unwrap_or(0),
+        creation_time: input.metadata().get("creation_time").map(|time| time.to_string()),
+        audio_streams: audio_streams(&input, duration),
+        ..Default::default()
+    };
+
+    // Find the video stream, ignoring cover art embedded in music files
+    let stream = input
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .filter(|stream| !is_cover_art(stream))
+        .or_else(|| {
+            input.streams().find(|stream| {
+                stream.parameters().medium() == ffmpeg::media::Type::Video && !is_cover_art(stream)
+            })
+        });
+    let Some(stream) = stream else {
+        // Audio-only file, e.g. a music bed
+        let Some(first_audio) = metadata.audio_streams.first() else {
+            return Err("No video or audio stream found in file".to_string());
+        };
+        metadata.media_kind = MediaKind::Audio;
+        metadata.codec = first_audio.codec.clone();
+        return Ok(metadata);
+    };
+
+    if is_image_container(&container) {
+        // Still images have no length of their own; the user picks one
+        metadata.media_kind = MediaKind::Image;
+        metadata.duration = DEFAULT_IMAGE_DURATION;
+    } else {
+        metadata.avg_frame_rate = rational_to_f64(stream.avg_frame_rate());
+        metadata.real_frame_rate = rational_to_f64(stream.rate());
+        metadata.is_vfr = is_variable_frame_rate(metadata.avg_frame_rate, metadata.real_frame_rate);
+    }
+    if metadata.creation_time.is_none() {
+        metadata.creation_time = stream.metadata().get("creation_time").map(|time| time.to_string());
+    }
+
+    // Rotation comes from the display matrix side data (e.g. portrait phone videos)
+    metadata.rotation = stream
+        .side_data()
+        .find(|side_data| side_data.kind() == ffmpeg::packet::side_data::Type::DisplayMatrix)
+        .and_then(|side_data| display_matrix(side_data.data()))
+        .map(display_rotation)
+        .unwrap_or(0);
+
+    // Get codec context from stream parameters
+    let codec = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+        .map_err(|e| format!("Failed to get codec context: {}", e))?;
+
+    // Get codec name before consuming codec
+    metadata.codec = codec
+        .codec()
+        .map(|c| c.name().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Get video decoder to access video properties (this consumes codec)
+    let video = codec
+        .decoder()
+        .video()
+        .map_err(|e| format!("Failed to get video decoder: {}", e))?;
+
+    // Get resolution
+    metadata.width = video.width();
+    metadata.height = video.height();
+    metadata.resolution = format!("{}x{}", video.width(), video.height());
+
+    // Prefer the video stream's bit rate, falling back to the container's overall rate
+    metadata.bit_rate = match video.bit_rate() {
+        0 => input.bit_rate().max(0) as u64,
+        stream_rate => stream_rate as u64,
+    };
+
+    metadata.sample_aspect_ratio = match rational_to_f64(video.aspect_ratio()) {
+        ratio if ratio > 0.0 => ratio,
+        _ => 1.0,
+    };
+    metadata.pix_fmt = video.format().descriptor().map(|descriptor| descriptor.name().to_string());
+    metadata.color_space = video.color_space().name().map(str::to_string);
+    metadata.color_transfer = video.color_transfer_characteristic().name().map(str::to_string);
+    metadata.color_primaries = video.color_primaries().name().map(str::to_string);
+
+    Ok(metadata)
+}
+
+/// Album art attached to audio files shows up as a single-frame video stream
+fn is_cover_art(stream: &ffmpeg::Stream) -> bool {
+    stream.disposition().contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+}
+
+/// Describes every audio stream in the file, in order
+/// Streams FFmpeg can't decode are still listed, with whatever the container reports
+fn audio_streams(input: &ffmpeg::format::context::Input, container_duration: f64) -> Vec<AudioStream> {
+    input
+        .streams()
+        .filter(|stream| stream.parameters().medium() == ffmpeg::media::Type::Audio)
+        .enumerate()
+        .map(|(index, stream)| {
+            let metadata = stream.metadata();
+            let tag = |key: &str| metadata.get(key).map(|value| value.to_string());
+
+            // Durations are in the stream's time base; unknown (AV_NOPTS_VALUE) falls back to the file's
+            let duration = match stream.duration() {
+                duration if duration > 0 => duration as f64 * rational_to_f64(stream.time_base()),
+                _ => container_duration,
+            };
+
+            let mut audio_stream = AudioStream {
+                index,
+                codec: stream.parameters().id().name().to_string(),
+                language: tag("language"),
+                title: tag("title"),
+                duration,
+                is_default: stream.disposition().contains(ffmpeg::format::stream::Disposition::DEFAULT),
+                ..Default::default()
+            };
+            let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+                .and_then(|context| context.decoder().audio());
+            if let Ok(audio) = decoder {
+                audio_stream.sample_rate = audio.rate();
+                audio_stream.channels = audio.channels();
+                audio_stream.channel_layout = channel_layout_name(audio.channel_layout());
+                audio_stream.bit_rate = audio.bit_rate() as u64;
+            }
+            audio_stream
+        })
+        .collect()
+}
+
+/// FFmpeg's name for the common channel layouts
+fn channel_layout_name(layout: ffmpeg::ChannelLayout) -> Option<String> {
+    use ffmpeg::ChannelLayout;
+    let names = [
+        (ChannelLayout::MONO, "mono"),
+        (ChannelLayout::STEREO, "stereo"),
+        (ChannelLayout::_2POINT1, "2.1"),
+        (ChannelLayout::SURROUND, "3.0"),
+        (ChannelLayout::QUAD, "quad"),
+        (ChannelLayout::_4POINT0, "4.0"),
+        (ChannelLayout::_5POINT0_BACK, "5.0"),
+        (ChannelLayout::_5POINT0, "5.0(side)"),
+        (ChannelLayout::_5POINT1_BACK, "5.1"),
+        (ChannelLayout::_5POINT1, "5.1(side)"),
+        (ChannelLayout::_7POINT1, "7.1"),
+    ];
+    names
+        .into_iter()
+        .find(|(known, _)| *known == layout)
+        .map(|(_, name)| name.to_string())
+}
+
+impl VideoMetadata {
+    /// Audio stream used when a clip doesn't choose one: the file's default, else the first
+    pub fn default_audio_stream(&self) -> Option<usize> {
+        self.audio_streams
+            .iter()
+            .find(|stream| stream.is_default)
+            .or_else(|| self.audio_streams.first())
+            .map(|stream| stream.index)
+    }
+
+    /// Size of the picture as displayed: rotation applied and non-square pixels stretched
+    /// Falls back to `resolution` for metadata saved before width/height were recorded
+    pub fn display_size(&self) -> (u32, u32) {
+        let (width, height) = if self.width > 0 && self.height > 0 {
+            (self.width, self.height)
+        } else {
+            parse_resolution(&self.resolution).unwrap_or((0, 0))
+        };
+        let sample_aspect_ratio = if self.sample_aspect_ratio > 0.0 { self.sample_aspect_ratio } else { 1.0 };
+        let width = (width as f64 * sample_aspect_ratio).round() as u32;
+
+        if self.rotation % 180 == 90 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// Parses a "WxH" resolution string
+fn parse_resolution(resolution: &str) -> Option<(u32, u32)> {
+    let (width, height) = resolution.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// Image demuxers: `image2` for files matched by extension, `<codec>_pipe` for probed ones
+fn is_image_container(format_name: &str) -> bool {
+    format_name == "image2" || format_name.ends_with("_pipe")
+}
+
+/// Converts an FFmpeg rational, treating unset values (0/0, x/0) as 0
+fn rational_to_f64(rational: ffmpeg::Rational) -> f64 {
+    if rational.numerator() == 0 || rational.denominator() == 0 {
+        0.0
+    } else {
+        f64::from(rational)
+    }
+}
+
+/// Streams whose average frame rate differs from their base (real) rate have variable frame timing
+fn is_variable_frame_rate(avg_frame_rate: f64, real_frame_rate: f64) -> bool {
+    if avg_frame_rate <= 0.0 || real_frame_rate <= 0.0 {
+        return false;
+    }
+    (avg_frame_rate - real_frame_rate).abs() / real_frame_rate > VFR_TOLERANCE
+}
+
+/// Decodes the 3x3 display matrix side data (nine native-endian i32 values)
+fn display_matrix(data: &[u8]) -> Option<[i32; 9]> {
+    if data.len() < 36 {
+        return None;
+    }
+    let mut matrix = [0i32; 9];
+    for (value, bytes) in matrix.iter_mut().zip(data.chunks_exact(4)) {
+        *value = i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    Some(matrix)
+}
+
+/// Clockwise rotation in degrees (0, 90, 180 or 270) needed to display the frame upright
+/// This is the negation of FFmpeg's av_display_rotation_get, which ffprobe reports (e.g. -90)
+fn display_rotation(matrix: [i32; 9]) -> i32 {
+    let scale_x = (matrix[0] as f64).hypot(matrix[3] as f64);
+    let scale_y = (matrix[1] as f64).hypot(matrix[4] as f64);
+    if scale_x == 0.0 || scale_y == 0.0 {
+        return 0;
+    }
+    let clockwise = (matrix[1] as f64 / scale_y).atan2(matrix[0] as f64 / scale_x).to_degrees();
+
+    // Snap to the nearest quarter turn
+    ((clockwise / 90.0).round() as i32 * 90).rem_euclid(360)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16.16 fixed-point matrix for a clockwise rotation, as written by FFmpeg's av_display_rotation_set
+    fn rotation_matrix(clockwise_degrees: f64) -> [i32; 9] {
+        let (sin, cos) = (-clockwise_degrees).to_radians().sin_cos();
+        let fixed = |value: f64| (value * 65536.0).round() as i32;
+        [fixed(cos), fixed(-sin), 0, fixed(sin), fixed(cos), 0, 0, 0, 1 << 30]
+    }
+
+    fn metadata(width: u32, height: u32) -> VideoMetadata {
+        VideoMetadata {
+            media_kind: MediaKind::Video,
+            filename: "clip.mp4".to_string(),
+            path: "/videos/clip.mp4".to_string(),
+            duration: 10.0,
+            resolution: format!("{}x{}", width, height),
+            codec: "h264".to_string(),
+            width,
+            height,
+            avg_frame_rate: 30.0,
+            real_frame_rate: 30.0,
+            is_vfr: false,
+            bit_rate: 0,
+            pix_fmt: None,
+            color_space: None,
+            color_transfer: None,
+            color_primaries: None,
+            rotation: 0,
+            sample_aspect_ratio: 1.0,
+            container: "mov,mp4,m4a,3gp,3g2,mj2".to_string(),
+            file_size: 0,
+            creation_time: None,
+            audio_streams: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reads_rotation_from_display_matrix() {
+        // Phones record portrait video as landscape frames that ffprobe reports as rotation -90
+        assert_eq!(display_rotation(rotation_matrix(90.0)), 90);
+        assert_eq!(display_rotation(rotation_matrix(-90.0)), 270);
+        assert_eq!(display_rotation(rotation_matrix(180.0)), 180);
+        assert_eq!(display_rotation(rotation_matrix(0.0)), 0);
+        assert_eq!(display_rotation([0; 9]), 0);
+    }
+
+    #[test]
+    fn decodes_display_matrix_bytes() {
+        let matrix = rotation_matrix(90.0);
+        let bytes: Vec<u8> = matrix.iter().flat_map(|value| value.to_ne_bytes()).collect();
+        assert_eq!(display_matrix(&bytes), Some(matrix));
+        assert_eq!(display_matrix(&bytes[..20]), None);
+    }
+
+    #[test]
+    fn recognizes_image_containers() {
+        assert!(is_image_container("image2"));
+        assert!(is_image_container("png_pipe"));
+        assert!(is_image_container("webp_pipe"));
+        assert!(!is_image_container("mov,mp4,m4a,3gp,3g2,mj2"));
+        assert!(!is_image_container("mp3"));
+    }
+
+    #[test]
+    fn detects_variable_frame_rate() {
+        assert!(!is_variable_frame_rate(29.97, 29.97));
+        assert!(!is_variable_frame_rate(30000.0 / 1001.0, 30.0 - 0.02));
+        assert!(is_variable_frame_rate(27.4, 30.0));
+        assert!(!is_variable_frame_rate(0.0, 30.0));
+    }
+
+    #[test]
+    fn default_audio_stream_prefers_the_flagged_stream() {
+        let mut metadata = metadata(1920, 1080);
+        assert_eq!(metadata.default_audio_stream(), None);
+
+        metadata.audio_streams = (0..3)
+            .map(|index| AudioStream { index, ..Default::default() })
+            .collect();
+        assert_eq!(metadata.default_audio_stream(), Some(0));
+
+        metadata.audio_streams[2].is_default = true;
+        assert_eq!(metadata.default_audio_stream(), Some(2));
+    }
+
+    #[test]
+    fn display_size_applies_rotation_and_sample_aspect_ratio() {
+        assert_eq!(metadata(1920, 1080).display_size(), (1920, 1080));
+
+        let mut portrait = metadata(1920, 1080);
+        portrait.rotation = 90;
+        assert_eq!(portrait.display_size(), (1080, 1920));
+
+        let mut anamorphic = metadata(1440, 1080);
+        anamorphic.sample_aspect_ratio = 4.0 / 3.0;
+        assert_eq!(anamorphic.display_size(), (1920, 1080));
+
+        // Metadata saved before width/height existed still has the resolution string
+        let mut legacy = metadata(0, 0);
+        legacy.resolution = "1280x720".to_string();
+        assert_eq!(legacy.display_size(), (1280, 720));
+    }
+}
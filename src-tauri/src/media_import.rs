@@ -0,0 +1,304 @@
+//! Imports many media files at once: expands folders, filters by extension and
+//! probes the remaining files in parallel
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::Serialize;
+
+use crate::{media, MediaKind, VideoMetadata};
+
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "webm", "mkv", "avi"];
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "m4a", "aac", "ogg"];
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Files probed at the same time; each probe opens the file with FFmpeg
+const MAX_PROBE_THREADS: usize = 4;
+
+/// Result of importing a batch of files
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    /// Metadata of every file that was probed successfully, in input order
+    pub imported: Vec<VideoMetadata>,
+    /// Supported files that could not be probed
+    pub errors: Vec<ImportIssue>,
+    /// Files that were not probed, e.g. unsupported extensions
+    pub skipped: Vec<ImportIssue>,
+}
+
+/// A file that was not imported, and why
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ImportIssue {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Payload of the `import_progress` event, sent as each file finishes probing
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ImportProgress {
+    pub path: String,
+    /// Files probed so far, including this one
+    pub completed: usize,
+    pub total: usize,
+    /// Why this file failed, None when it was imported
+    pub error: Option<String>,
+}
+
+/// Probes a media file, giving still images `image_duration` seconds instead of the default
+pub fn probe(path: &str, image_duration: Option<f64>) -> Result<VideoMetadata, String> {
+    let mut metadata = media::extract_video_metadata(path)?;
+    if metadata.media_kind == MediaKind::Image
+        && let Some(duration) = image_duration.filter(|duration| *duration > 0.0)
+    {
+        metadata.duration = duration;
+    }
+    Ok(metadata)
+}
+
+/// Whether the file's extension is one the app imports
+pub fn is_supported(path: &Path) -> bool {
+    let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+        return false;
+    };
+    let extension = extension.to_lowercase();
+    [VIDEO_EXTENSIONS, AUDIO_EXTENSIONS, IMAGE_EXTENSIONS]
+        .iter()
+        .any(|extensions| extensions.contains(&extension.as_str()))
+}
+
+/// Expands folders (recursively, skipping hidden entries) and sorts out unsupported files
+/// Each folder is read once, so symlinks looping back to a parent folder are harmless
+/// Returns the files to probe and the skipped ones
+pub fn collect_files(paths: &[PathBuf]) -> (Vec<PathBuf>, Vec<ImportIssue>) {
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut visited = HashSet::new();
+    for path in paths {
+        collect_path(path, &mut files, &mut skipped, &mut visited);
+    }
+    (files, skipped)
+}
+
+/// `visited` holds the canonical paths of the folders read so far
+fn collect_path(
+    path: &Path,
+    files: &mut Vec<PathBuf>,
+    skipped: &mut Vec<ImportIssue>,
+    visited: &mut HashSet<PathBuf>,
+) {
+    let skip = |reason: String| ImportIssue {
+        path: path.to_string_lossy().to_string(),
+        reason,
+    };
+
+    if path.is_dir() {
+        match fs::canonicalize(path) {
+            Ok(canonical) => {
+                if !visited.insert(canonical) {
+                    return;
+                }
+            }
+            Err(e) => {
+                skipped.push(skip(format!("Failed to read folder: {}", e)));
+                return;
+            }
+        }
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                skipped.push(skip(format!("Failed to read folder: {}", e)));
+                return;
+            }
+        };
+        let mut children: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|child| !is_hidden(child))
+            .collect();
+        children.sort();
+        for child in children {
+            collect_path(&child, files, skipped, visited);
+        }
+    } else if !path.exists() {
+        skipped.push(skip("File not found".to_string()));
+    } else if !is_supported(path) {
+        let reason = match path.extension() {
+            Some(extension) => format!("Unsupported file type .{}", extension.to_string_lossy()),
+            None => "Unsupported file type (no extension)".to_string(),
+        };
+        skipped.push(skip(reason));
+    } else {
+        files.push(path.to_path_buf());
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Probes files on a few worker threads, calling `on_progress` as each one finishes
+pub fn probe_files<P, F>(files: &[PathBuf], probe: P, on_progress: F) -> (Vec<VideoMetadata>, Vec<ImportIssue>)
+where
+    P: Fn(&str) -> Result<VideoMetadata, String> + Sync,
+    F: Fn(ImportProgress) + Sync,
+{
+    // One slot per file so results come back in input order
+    let results: Vec<Mutex<Option<Result<VideoMetadata, String>>>> =
+        files.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map_or(1, |count| count.get())
+        .clamp(1, MAX_PROBE_THREADS)
+        .min(files.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(file) = files.get(index) else { break };
+                    let path = file.to_string_lossy().to_string();
+                    let result = probe(&path);
+                    on_progress(ImportProgress {
+                        path,
+                        completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                        total: files.len(),
+                        error: result.as_ref().err().cloned(),
+                    });
+                    *results[index].lock().unwrap() = Some(result);
+                }
+            });
+        }
+    });
+
+    let mut imported = Vec::new();
+    let mut errors = Vec::new();
+    for (file, result) in files.iter().zip(results) {
+        match result.into_inner().unwrap() {
+            Some(Ok(metadata)) => imported.push(metadata),
+            Some(Err(reason)) => errors.push(ImportIssue {
+                path: file.to_string_lossy().to_string(),
+                reason,
+            }),
+            None => {}
+        }
+    }
+    (imported, errors)
+}
+
+/// Imports files and folders, reporting progress per probed file
+pub fn import_paths<F>(paths: &[PathBuf], image_duration: Option<f64>, on_progress: F) -> ImportReport
+where
+    F: Fn(ImportProgress) + Sync,
+{
+    let (files, skipped) = collect_files(paths);
+    let (imported, errors) = probe_files(&files, |path| probe(path, image_duration), on_progress);
+    ImportReport {
+        imported,
+        errors,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn recognizes_supported_extensions() {
+        assert!(is_supported(Path::new("/clips/take1.MP4")));
+        assert!(is_supported(Path::new("music.flac")));
+        assert!(is_supported(Path::new("logo.jpeg")));
+        assert!(!is_supported(Path::new("notes.txt")));
+        assert!(!is_supported(Path::new("README")));
+    }
+
+    #[test]
+    fn expands_folders_and_skips_unsupported_files() {
+        let root = temp_dir("collect");
+        touch(&root.join("b.mov"));
+        touch(&root.join("a.mp4"));
+        touch(&root.join("notes.txt"));
+        touch(&root.join("day2/music.mp3"));
+        touch(&root.join(".cache/hidden.mp4"));
+
+        let missing = root.join("gone.mp4");
+        let (files, skipped) = collect_files(&[root.clone(), missing.clone()]);
+
+        assert_eq!(
+            files,
+            [root.join("a.mp4"), root.join("b.mov"), root.join("day2/music.mp3")]
+        );
+        assert_eq!(
+            skipped,
+            [
+                ImportIssue {
+                    path: root.join("notes.txt").to_string_lossy().to_string(),
+                    reason: "Unsupported file type .txt".to_string(),
+                },
+                ImportIssue {
+                    path: missing.to_string_lossy().to_string(),
+                    reason: "File not found".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_each_folder_once_through_looping_symlinks() {
+        let root = temp_dir("symlink_loop");
+        touch(&root.join("day1/a.mp4"));
+        std::os::unix::fs::symlink(&root, root.join("day1/loop")).unwrap();
+
+        let (files, skipped) = collect_files(&[root.clone(), root.join("day1")]);
+        assert_eq!(files, [root.join("day1/a.mp4")]);
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn probes_every_file_and_keeps_input_order() {
+        let files: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("clip{}.mp4", i))).collect();
+        let progress = Mutex::new(Vec::new());
+
+        let (imported, errors) = probe_files(
+            &files,
+            |path| match path {
+                "clip3.mp4" => Err("Failed to open media file: Invalid data".to_string()),
+                _ => Ok(VideoMetadata {
+                    path: path.to_string(),
+                    ..Default::default()
+                }),
+            },
+            |update| progress.lock().unwrap().push(update),
+        );
+
+        let paths: Vec<&str> = imported.iter().map(|metadata| metadata.path.as_str()).collect();
+        assert_eq!(paths.len(), 9);
+        assert!(paths.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", paths);
+        assert_eq!(
+            errors,
+            [ImportIssue {
+                path: "clip3.mp4".to_string(),
+                reason: "Failed to open media file: Invalid data".to_string(),
+            }]
+        );
+
+        let progress = progress.into_inner().unwrap();
+        let mut completed: Vec<usize> = progress.iter().map(|update| update.completed).collect();
+        completed.sort();
+        assert_eq!(completed, (1..=10).collect::<Vec<_>>());
+        assert!(progress.iter().all(|update| update.total == 10));
+        assert_eq!(progress.iter().filter(|update| update.error.is_some()).count(), 1);
+    }
+}
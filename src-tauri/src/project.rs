@@ -0,0 +1,364 @@
+//! Project files: the media library, timeline, export settings and markers saved as
+//! versioned JSON
+//!
+//! Every schema change bumps `PROJECT_VERSION` and appends a step to `MIGRATIONS`, so
+//! projects saved by older releases are upgraded when they are opened
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{ClipforgeError, ErrorKind};
+use crate::fs_util::write_atomically;
+use crate::{ExportRequest, TimelineClip, VideoMetadata};
+
+/// Schema version written by this build; the first project format is version 1
+pub const PROJECT_VERSION: u64 = 1;
+
+/// Extension of project files
+pub const PROJECT_EXTENSION: &str = "clipforge";
+
+/// Upgrades a project by one schema version
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// `MIGRATIONS[i]` upgrades version `i + 1` to `i + 2`, so the list is one shorter than
+/// `PROJECT_VERSION`
+const MIGRATIONS: &[Migration] = &[];
+
+/// Everything needed to reopen an editing session
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Project {
+    pub version: u64,
+    pub media: Vec<ProjectMedia>,
+    /// Timeline clips; `clip_id` is the `id` of an entry in `media`
+    pub clips: Vec<TimelineClip>,
+    pub export_settings: ExportSettings,
+    pub markers: Vec<Marker>,
+}
+
+/// A media library entry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectMedia {
+    pub id: String,
+    #[serde(flatten)]
+    pub metadata: VideoMetadata,
+}
+
+/// Export options last chosen for the project
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub format: String,     // "mp4", "webm", or "mov"
+    pub resolution: String, // "Source", "720p", "1080p", "1440p", or "4K"
+    pub output_path: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: "mp4".to_string(),
+            resolution: "1080p".to_string(),
+            output_path: None,
+            video_codec: None,
+            audio_codec: None,
+        }
+    }
+}
+
+/// A labelled point on the timeline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Marker {
+    pub id: String,
+    pub time: f64, // Seconds from the start of the timeline
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl Project {
+    /// Export request for the timeline with the project's export settings
+    /// Timeline clips refer to media by path, as export requests expect; `output_path`
+    /// overrides the project's last export destination
+    pub fn export_request(&self, output_path: Option<String>) -> Result<(ExportRequest, Vec<VideoMetadata>), ClipforgeError> {
+        let output_path = output_path
+            .or_else(|| self.export_settings.output_path.clone())
+            .ok_or_else(|| {
+                ClipforgeError::new(ErrorKind::InvalidInput, "Project has no export destination; choose an output path")
+            })?;
+        let paths: HashMap<&str, &str> = self
+            .media
+            .iter()
+            .map(|media| (media.id.as_str(), media.metadata.path.as_str()))
+            .collect();
+        let clips = self
+            .clips
+            .iter()
+            .map(|clip| {
+                let path = paths.get(clip.clip_id.as_str()).ok_or_else(|| {
+                    ClipforgeError::new(
+                        ErrorKind::InvalidProject,
+                        format!("Timeline clip {} refers to media {} which is not in the project", clip.id, clip.clip_id),
+                    )
+                })?;
+                Ok(TimelineClip {
+                    clip_id: path.to_string(),
+                    ..clip.clone()
+                })
+            })
+            .collect::<Result<Vec<_>, ClipforgeError>>()?;
+
+        let request = ExportRequest {
+            clips,
+            output_path,
+            resolution: self.export_settings.resolution.clone(),
+            format: self.export_settings.format.clone(),
+            video_codec: self.export_settings.video_codec.clone(),
+            audio_codec: self.export_settings.audio_codec.clone(),
+        };
+        let clips_data = self.media.iter().map(|media| media.metadata.clone()).collect();
+        Ok((request, clips_data))
+    }
+}
+
+/// Writes a project, stamping it with the current schema version
+pub fn save(path: &Path, project: &Project) -> Result<(), ClipforgeError> {
+    let project = Project {
+        version: PROJECT_VERSION,
+        ..project.clone()
+    };
+    validate(&project).map_err(invalid_project)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| ClipforgeError::io("Failed to create project directory", parent, e))?;
+    }
+    let json = serde_json::to_string_pretty(&project)
+        .map_err(|e| ClipforgeError::new(ErrorKind::Internal, format!("Failed to serialize project: {}", e)))?;
+    write_atomically(path, json).map_err(|e| ClipforgeError::io("Failed to write project", path, e))
+}
+
+/// Reads a project, upgrading it from older schema versions
+/// Relative media paths (as written by archives) are resolved against the project's folder
+pub fn load(path: &Path) -> Result<Project, ClipforgeError> {
+    let json = fs::read_to_string(path).map_err(|e| ClipforgeError::io("Failed to read project", path, e))?;
+    let mut project = parse(&json).map_err(|e| e.with_path(path))?;
+    if let Some(folder) = path.parent() {
+        for media in &mut project.media {
+            let media_path = Path::new(&media.metadata.path);
+            if !media.metadata.path.is_empty() && media_path.is_relative() {
+                media.metadata.path = folder.join(media_path).to_string_lossy().to_string();
+            }
+        }
+    }
+    Ok(project)
+}
+
+/// Parses project JSON of any supported schema version
+pub fn parse(json: &str) -> Result<Project, ClipforgeError> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| invalid_project(format!("Invalid project file: {}", e)))?;
+    let project: Project = serde_json::from_value(migrate(value).map_err(invalid_project)?)
+        .map_err(|e| invalid_project(format!("Invalid project file: {}", e)))?;
+    validate(&project).map_err(invalid_project)?;
+    Ok(project)
+}
+
+fn invalid_project(message: String) -> ClipforgeError {
+    ClipforgeError::new(ErrorKind::InvalidProject, message)
+}
+
+/// Applies every migration from the file's version up to `PROJECT_VERSION`
+fn migrate(project: Value) -> Result<Value, String> {
+    migrate_with(project, MIGRATIONS)
+}
+
+/// Upgrades a project to the version after the last of `migrations`
+fn migrate_with(mut project: Value, migrations: &[Migration]) -> Result<Value, String> {
+    if !project.is_object() {
+        return Err("Invalid project file: expected a JSON object".to_string());
+    }
+    let latest = migrations.len() as u64 + 1;
+    let version = match project.get("version") {
+        None => return Err("Invalid project file: no schema version".to_string()),
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| format!("Invalid project version: {}", version))?,
+    };
+    if version > latest {
+        return Err(format!(
+            "Project was saved by a newer version of ClipForge (schema version {}, this version reads up to {})",
+            version, latest
+        ));
+    }
+
+    for (index, migration) in migrations.iter().enumerate().skip(version as usize - 1) {
+        let from = index as u64 + 1;
+        migration(&mut project).map_err(|e| format!("Failed to upgrade project from version {}: {}", from, e))?;
+        project["version"] = Value::from(from + 1);
+    }
+    Ok(project)
+}
+
+/// Checks that media ids are unique and every timeline clip refers to one
+fn validate(project: &Project) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for media in &project.media {
+        if !ids.insert(media.id.as_str()) {
+            return Err(format!("Duplicate media id {} in project", media.id));
+        }
+    }
+    for clip in &project.clips {
+        if !ids.contains(clip.clip_id.as_str()) {
+            return Err(format!(
+                "Timeline clip {} refers to media {} which is not in the project",
+                clip.id, clip.clip_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::MediaKind;
+
+    fn media(id: &str, path: &str) -> ProjectMedia {
+        test_support::media(id, path, MediaKind::Video, 12.5)
+    }
+
+    fn clip(id: &str, clip_id: &str) -> TimelineClip {
+        TimelineClip {
+            audio_stream: Some(1),
+            ..test_support::clip(id, clip_id, 0, 0.0, 1.0, 6.0)
+        }
+    }
+
+    #[test]
+    fn saves_and_loads_projects() {
+        let dir = test_support::temp_dir("project");
+        let path = dir.join("demo.clipforge");
+        let project = Project {
+            media: vec![media("clip-1", "/videos/intro.mp4")],
+            clips: vec![clip("timeline-1", "clip-1")],
+            export_settings: ExportSettings {
+                format: "webm".to_string(),
+                ..Default::default()
+            },
+            markers: vec![Marker {
+                id: "marker-1".to_string(),
+                time: 3.25,
+                label: "Chapter 1".to_string(),
+                color: None,
+            }],
+            ..Default::default()
+        };
+
+        save(&path, &project).unwrap();
+        let loaded = load(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(loaded.version, PROJECT_VERSION);
+        assert_eq!(loaded.media[0].id, "clip-1");
+        assert_eq!(loaded.media[0].metadata.path, "/videos/intro.mp4");
+        assert_eq!(loaded.media[0].metadata.duration, 12.5);
+        assert_eq!(loaded.clips[0].clip_id, "clip-1");
+        assert_eq!(loaded.clips[0].audio_stream, Some(1));
+        assert_eq!(loaded.export_settings.format, "webm");
+        assert_eq!(loaded.export_settings.resolution, "1080p");
+        assert_eq!(loaded.markers, project.markers);
+    }
+
+    /// Fixture migrations: version 1 to 2 renames `title` to `name`, 2 to 3 adds `tags`
+    fn rename_title(project: &mut Value) -> Result<(), String> {
+        let object = project.as_object_mut().unwrap();
+        let title = object.remove("title").ok_or("missing title")?;
+        object.insert("name".to_string(), title);
+        Ok(())
+    }
+
+    fn add_tags(project: &mut Value) -> Result<(), String> {
+        project["tags"] = Value::Array(Vec::new());
+        Ok(())
+    }
+
+    #[test]
+    fn migrations_match_the_schema_version() {
+        assert_eq!(MIGRATIONS.len() as u64 + 1, PROJECT_VERSION);
+    }
+
+    #[test]
+    fn upgrades_older_projects_one_version_at_a_time() {
+        let migrations: &[Migration] = &[rename_title, add_tags];
+
+        let project = migrate_with(serde_json::json!({ "version": 1, "title": "Demo" }), migrations).unwrap();
+        assert_eq!(project, serde_json::json!({ "version": 3, "name": "Demo", "tags": [] }));
+
+        let project = migrate_with(serde_json::json!({ "version": 2, "name": "Demo" }), migrations).unwrap();
+        assert_eq!(project, serde_json::json!({ "version": 3, "name": "Demo", "tags": [] }));
+
+        let err = migrate_with(serde_json::json!({ "version": 1 }), migrations).unwrap_err();
+        assert_eq!(err, "Failed to upgrade project from version 1: missing title");
+    }
+
+    #[test]
+    fn rejects_projects_without_a_version() {
+        let err = parse(r#"{ "media": [], "clips": [] }"#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidProject);
+        assert_eq!(err.message, "Invalid project file: no schema version");
+        assert!(parse(r#"{ "version": 0 }"#).is_err());
+    }
+
+    #[test]
+    fn rejects_projects_from_newer_versions() {
+        let err = parse(r#"{ "version": 99 }"#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidProject);
+        assert!(err.message.contains("newer version of ClipForge"), "{}", err);
+    }
+
+    #[test]
+    fn builds_export_requests_with_media_paths() {
+        let project = Project {
+            media: vec![media("clip-1", "/videos/intro.mp4"), media("clip-2", "/videos/outro.mp4")],
+            clips: vec![clip("timeline-1", "clip-2")],
+            export_settings: ExportSettings {
+                output_path: Some("/exports/last.mp4".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (request, clips_data) = project.export_request(None).unwrap();
+        assert_eq!(request.output_path, "/exports/last.mp4");
+        assert_eq!(request.clips[0].clip_id, "/videos/outro.mp4");
+        assert_eq!(request.clips[0].trim_start, 1.0);
+        assert_eq!(request.resolution, "1080p");
+        assert_eq!(clips_data.len(), 2);
+
+        let (request, _) = project.export_request(Some("/tmp/out.mp4".to_string())).unwrap();
+        assert_eq!(request.output_path, "/tmp/out.mp4");
+
+        let unsaved = Project { export_settings: ExportSettings::default(), ..project };
+        assert!(unsaved.export_request(None).is_err());
+    }
+
+    #[test]
+    fn rejects_clips_without_media() {
+        let project = Project {
+            media: vec![media("clip-1", "/videos/intro.mp4")],
+            clips: vec![clip("timeline-1", "clip-2")],
+            ..Default::default()
+        };
+        let err = save(Path::new("/nonexistent/demo.clipforge"), &project).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidProject);
+        assert_eq!(err.message, "Timeline clip timeline-1 refers to media clip-2 which is not in the project");
+    }
+}
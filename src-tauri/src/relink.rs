@@ -0,0 +1,210 @@
+//! Finds project media whose files have moved and points it at replacement files
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::media_import;
+use crate::project::{Project, ProjectMedia};
+use crate::{MediaKind, VideoMetadata};
+
+/// Largest duration difference, in seconds, between a file and its replacement
+const DURATION_TOLERANCE: f64 = 0.5;
+
+/// A media entry whose file no longer exists
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MissingMedia {
+    pub id: String,
+    pub path: String,
+    pub filename: String,
+    pub duration: f64,
+    pub file_size: u64,
+}
+
+/// Outcome of relinking against a folder
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RelinkReport {
+    /// Updated media entries, keeping their ids
+    pub relinked: Vec<ProjectMedia>,
+    /// Entries still missing, with the reason no replacement was used
+    pub unresolved: Vec<Unresolved>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Unresolved {
+    pub id: String,
+    pub reason: String,
+}
+
+/// Media entries whose files are gone
+pub fn find_missing(project: &Project) -> Vec<MissingMedia> {
+    project
+        .media
+        .iter()
+        .filter(|media| !Path::new(&media.metadata.path).is_file())
+        .map(|media| MissingMedia {
+            id: media.id.clone(),
+            path: media.metadata.path.clone(),
+            filename: media.metadata.filename.clone(),
+            duration: media.metadata.duration,
+            file_size: media.metadata.file_size,
+        })
+        .collect()
+}
+
+/// Checks that a probed replacement can stand in for the original media
+pub fn check_compatible(original: &VideoMetadata, replacement: &VideoMetadata) -> Result<(), String> {
+    if original.media_kind != replacement.media_kind {
+        return Err(format!(
+            "{} is {:?} media but the original was {:?}",
+            replacement.filename, replacement.media_kind, original.media_kind
+        ));
+    }
+    // Image durations are chosen by the user, not read from the file
+    if original.media_kind != MediaKind::Image
+        && original.duration > 0.0
+        && (original.duration - replacement.duration).abs() > DURATION_TOLERANCE
+    {
+        return Err(format!(
+            "{} is {:.1}s long but the original was {:.1}s",
+            replacement.filename, replacement.duration, original.duration
+        ));
+    }
+    Ok(())
+}
+
+/// Points a media entry at a replacement file once the probe shows it is compatible
+pub fn relink<P>(media: &ProjectMedia, new_path: &str, probe: P) -> Result<ProjectMedia, String>
+where
+    P: Fn(&str) -> Result<VideoMetadata, String>,
+{
+    let mut metadata = probe(new_path)?;
+    check_compatible(&media.metadata, &metadata)?;
+    if metadata.media_kind == MediaKind::Image {
+        metadata.duration = media.metadata.duration;
+    }
+    Ok(ProjectMedia {
+        id: media.id.clone(),
+        metadata,
+    })
+}
+
+/// Searches `root` recursively for files named like each missing entry, preferring ones
+/// of the same size, and relinks to the first compatible one
+pub fn relink_folder<P>(missing: &[ProjectMedia], root: &Path, probe: P) -> RelinkReport
+where
+    P: Fn(&str) -> Result<VideoMetadata, String>,
+{
+    let (files, _) = media_import::collect_files(&[root.to_path_buf()]);
+    let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for file in files {
+        if let Some(name) = file.file_name() {
+            by_name.entry(name.to_string_lossy().to_lowercase()).or_default().push(file);
+        }
+    }
+
+    let mut report = RelinkReport::default();
+    for media in missing {
+        let unresolved = |reason: String| Unresolved {
+            id: media.id.clone(),
+            reason,
+        };
+        let Some(candidates) = by_name.get(&media.metadata.filename.to_lowercase()) else {
+            report.unresolved.push(unresolved(format!(
+                "No file named {} in {}",
+                media.metadata.filename,
+                root.display()
+            )));
+            continue;
+        };
+
+        let mut candidates = candidates.clone();
+        candidates.sort_by_key(|candidate| !same_size(candidate, media.metadata.file_size));
+        let mut last_error = String::new();
+        let relinked = candidates.iter().find_map(|candidate| {
+            relink(media, &candidate.to_string_lossy(), &probe)
+                .inspect_err(|e| last_error = e.clone())
+                .ok()
+        });
+        match relinked {
+            Some(relinked) => report.relinked.push(relinked),
+            None => report.unresolved.push(unresolved(last_error)),
+        }
+    }
+    report
+}
+
+/// Whether the file has the recorded size; unknown sizes (0) never match
+fn same_size(path: &Path, file_size: u64) -> bool {
+    file_size > 0 && fs::metadata(path).is_ok_and(|metadata| metadata.len() == file_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, metadata, temp_dir};
+
+    fn media(path: &str, duration: f64, file_size: u64) -> ProjectMedia {
+        let mut media = test_support::media("clip-1", path, MediaKind::Video, duration);
+        media.metadata.file_size = file_size;
+        media
+    }
+
+    #[test]
+    fn checks_kind_and_duration() {
+        let original = metadata("/old/take.mp4", MediaKind::Video, 12.0);
+        assert_eq!(check_compatible(&original, &metadata("/new/take.mp4", MediaKind::Video, 12.3)), Ok(()));
+        assert_eq!(
+            check_compatible(&original, &metadata("/new/take.mp4", MediaKind::Video, 20.0)),
+            Err("take.mp4 is 20.0s long but the original was 12.0s".to_string())
+        );
+        assert!(check_compatible(&original, &metadata("/new/take.mp3", MediaKind::Audio, 12.0)).is_err());
+
+        let image = metadata("/old/logo.png", MediaKind::Image, 8.0);
+        assert_eq!(check_compatible(&image, &metadata("/new/logo.png", MediaKind::Image, 5.0)), Ok(()));
+    }
+
+    #[test]
+    fn keeps_the_id_and_image_duration() {
+        let mut logo = media("/old/logo.png", 8.0, 0);
+        logo.metadata.media_kind = MediaKind::Image;
+        let relinked = relink(&logo, "/new/logo.png", |path| Ok(metadata(path, MediaKind::Image, 5.0))).unwrap();
+        assert_eq!(relinked.id, "clip-1");
+        assert_eq!(relinked.metadata.path, "/new/logo.png");
+        assert_eq!(relinked.metadata.duration, 8.0);
+    }
+
+    #[test]
+    fn relinks_from_a_folder_by_name_and_size() {
+        let root = temp_dir("relink_folder");
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/Take.mp4"), b"short").unwrap();
+        fs::write(root.join("b/take.mp4"), b"the real one").unwrap();
+
+        let missing = [media("/old/take.mp4", 12.0, 12), media("/old/gone.mp4", 3.0, 0)];
+        let report = relink_folder(&missing, &root, |path| Ok(metadata(path, MediaKind::Video, 12.0)));
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(report.relinked.len(), 1);
+        assert_eq!(report.relinked[0].metadata.path, root.join("b/take.mp4").to_string_lossy());
+        assert_eq!(report.unresolved.len(), 1);
+        assert!(report.unresolved[0].reason.starts_with("No file named gone.mp4"), "{:?}", report.unresolved);
+    }
+
+    #[test]
+    fn reports_why_candidates_were_rejected() {
+        let root = temp_dir("relink_reject");
+        fs::write(root.join("take.mp4"), b"").unwrap();
+
+        let report = relink_folder(&[media("/old/take.mp4", 12.0, 0)], &root, |path| {
+            Ok(metadata(path, MediaKind::Video, 30.0))
+        });
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(report.relinked.is_empty());
+        assert_eq!(report.unresolved[0].reason, "take.mp4 is 30.0s long but the original was 12.0s");
+    }
+}
@@ -0,0 +1,49 @@
+//! Fixtures shared by the unit tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::project::ProjectMedia;
+use crate::{MediaKind, TimelineClip, VideoMetadata};
+
+/// Empty scratch directory for one test, unique to this test run
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("clipforge_tests")
+        .join(format!("{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Timeline clip of `clip_id` from `trim_start` to `trim_end`, placed at `start_time`
+pub fn clip(id: &str, clip_id: &str, track: u32, start_time: f64, trim_start: f64, trim_end: f64) -> TimelineClip {
+    TimelineClip {
+        id: id.to_string(),
+        clip_id: clip_id.to_string(),
+        track,
+        start_time,
+        trim_start,
+        trim_end,
+        duration: trim_end - trim_start,
+        transform: None,
+        audio_stream: None,
+    }
+}
+
+pub fn metadata(path: &str, media_kind: MediaKind, duration: f64) -> VideoMetadata {
+    VideoMetadata {
+        media_kind,
+        filename: Path::new(path).file_name().unwrap().to_string_lossy().to_string(),
+        path: path.to_string(),
+        duration,
+        ..Default::default()
+    }
+}
+
+pub fn media(id: &str, path: &str, media_kind: MediaKind, duration: f64) -> ProjectMedia {
+    ProjectMedia {
+        id: id.to_string(),
+        metadata: metadata(path, media_kind, duration),
+    }
+}
@@ -0,0 +1,217 @@
+//! Renders small lavfi-generated clips through the export pipeline without a Tauri app
+//! Skipped when FFmpeg is not on PATH
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clipforge_lib::export::{self, ExportEvent, ExportJob, ExportJobs, ExportOutcome, ExportSink, ExportStage};
+use clipforge_lib::ffmpeg_capabilities::CapabilityCache;
+use clipforge_lib::ffmpeg_locator::{FfmpegLocator, Tool, ToolLocator};
+use clipforge_lib::{media, ExportRequest, TimelineClip, VideoMetadata};
+
+/// Output durations may differ from the timeline by about a frame of audio or video
+const DURATION_TOLERANCE: f64 = 0.15;
+
+/// Records every event it receives
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<ExportEvent>>>);
+
+impl ExportSink for RecordingSink {
+    fn send(&self, event: ExportEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+struct TestEnv {
+    dir: PathBuf,
+    locator: FfmpegLocator,
+    capabilities: CapabilityCache,
+}
+
+impl TestEnv {
+    /// None when there is no FFmpeg to test with
+    fn new(name: &str) -> Option<Self> {
+        let locator = FfmpegLocator::new(Vec::new(), None, None, std::env::var_os("PATH"));
+        if let Err(e) = locator.locate(Tool::Ffmpeg) {
+            eprintln!("Skipping export test: {}", e);
+            return None;
+        }
+        ffmpeg_next::init().unwrap();
+
+        let dir = std::env::temp_dir()
+            .join(format!("clipforge_export_tests-{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Some(Self {
+            dir,
+            locator,
+            capabilities: CapabilityCache::default(),
+        })
+    }
+
+    /// Generates a test pattern with a tone, or just the tone when `video` is false
+    fn lavfi_clip(&self, name: &str, seconds: f64, video: bool) -> String {
+        let path = self.dir.join(name);
+        let mut command = self.locator.locate(Tool::Ffmpeg).unwrap().command();
+        command.args(["-y", "-v", "error"]);
+        if video {
+            command.args(["-f", "lavfi", "-i", &format!("testsrc=size=320x240:rate=25:duration={}", seconds)]);
+        }
+        command.args(["-f", "lavfi", "-i", &format!("sine=frequency=440:sample_rate=48000:duration={}", seconds)]);
+        if video {
+            command.args(["-c:v", "mpeg4"]);
+        }
+        command.args(["-c:a", "aac"]).arg(&path);
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        path.to_string_lossy().to_string()
+    }
+
+    /// An MP4 export request, using fallback encoders if this FFmpeg lacks libx264
+    fn request(&self, clips: Vec<TimelineClip>, output: &Path) -> ExportRequest {
+        let capabilities = self.capabilities.get(&self.locator, false).unwrap();
+        let video_codec = (!capabilities.encoders.contains("libx264"))
+            .then(|| capabilities.fallback_for("libx264").unwrap_or("mpeg4").to_string());
+        ExportRequest {
+            clips,
+            output_path: output.to_string_lossy().to_string(),
+            resolution: "Source".to_string(),
+            format: "mp4".to_string(),
+            video_codec,
+            audio_codec: None,
+        }
+    }
+
+    /// Renders a request, waiting for FFmpeg to exit
+    fn render(&self, request: &ExportRequest) -> (ExportOutcome, Vec<ExportEvent>) {
+        let mut clips_data: Vec<VideoMetadata> = Vec::new();
+        for clip in &request.clips {
+            if !clips_data.iter().any(|c| c.path == clip.clip_id) {
+                clips_data.push(media::extract_video_metadata(&clip.clip_id).unwrap());
+            }
+        }
+        let (plan, ffmpeg_path) =
+            export::prepare_export(request, &clips_data, &self.locator, &self.capabilities).unwrap();
+
+        let sink = RecordingSink::default();
+        let (sender, receiver) = mpsc::channel();
+        let job = ExportJob {
+            ffmpeg_path,
+            plan,
+            output_path: request.output_path.clone(),
+            log_dir: Some(self.dir.join("logs")),
+        };
+        let reservation = ExportJobs::default().reserve(export::new_job_id()).unwrap();
+        export::start_export(reservation, job, sink.clone(), move |outcome| {
+            let _ = sender.send(outcome);
+        })
+        .unwrap();
+
+        let outcome = receiver.recv_timeout(Duration::from_secs(120)).expect("export did not finish");
+        let events = sink.0.lock().unwrap().clone();
+        (outcome, events)
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn clip(id: &str, path: &str, track: u32, start_time: f64, trim_start: f64, trim_end: f64) -> TimelineClip {
+    TimelineClip {
+        id: id.to_string(),
+        clip_id: path.to_string(),
+        track,
+        start_time,
+        trim_start,
+        trim_end,
+        duration: trim_end - trim_start,
+        transform: None,
+        audio_stream: None,
+    }
+}
+
+fn output_duration(path: &Path) -> f64 {
+    media::extract_video_metadata(&path.to_string_lossy()).unwrap().duration
+}
+
+#[test]
+fn concatenates_trimmed_clips_and_gaps() {
+    let Some(env) = TestEnv::new("concat") else {
+        return;
+    };
+    let first = env.lavfi_clip("first.mp4", 2.0, true);
+    let second = env.lavfi_clip("second.mp4", 2.0, true);
+    let output = env.dir.join("concat.mp4");
+
+    // 1.5s, a 0.5s gap, then 1s
+    let request = env.request(
+        vec![clip("a", &first, 0, 0.0, 0.0, 1.5), clip("b", &second, 0, 2.0, 0.5, 1.5)],
+        &output,
+    );
+    let (outcome, events) = env.render(&request);
+
+    assert_eq!(outcome, ExportOutcome::Completed);
+    let duration = output_duration(&output);
+    assert!((duration - 3.0).abs() < DURATION_TOLERANCE, "duration {}", duration);
+
+    let Some(ExportEvent::Progress(first_progress)) = events.first() else {
+        panic!("expected progress first, got {:?}", events.first());
+    };
+    assert_eq!(first_progress.stage, ExportStage::Starting);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        ExportEvent::Progress(progress) if progress.stage == ExportStage::Complete && progress.percent == 100
+    )));
+    assert!(matches!(events.last(), Some(ExportEvent::Completed(completed)) if completed.output_path == request.output_path));
+}
+
+#[test]
+fn extends_the_base_track_under_overlays_and_audio() {
+    let Some(env) = TestEnv::new("layers") else {
+        return;
+    };
+    let base = env.lavfi_clip("base.mp4", 2.0, true);
+    let overlay = env.lavfi_clip("overlay.mp4", 2.0, true);
+    let music = env.lavfi_clip("music.m4a", 3.0, false);
+    let output = env.dir.join("layers.mp4");
+
+    // The overlay ends at 3s and the audio at 3.5s, past the 2s base clip
+    let request = env.request(
+        vec![
+            clip("base", &base, 0, 0.0, 0.0, 2.0),
+            clip("overlay", &overlay, 1, 1.5, 0.0, 1.5),
+            clip("music", &music, 2, 0.5, 0.0, 3.0),
+        ],
+        &output,
+    );
+    let (outcome, _) = env.render(&request);
+
+    assert_eq!(outcome, ExportOutcome::Completed);
+    let duration = output_duration(&output);
+    assert!((duration - 3.5).abs() < DURATION_TOLERANCE, "duration {}", duration);
+}
+
+#[test]
+fn reports_ffmpeg_failures() {
+    let Some(env) = TestEnv::new("failure") else {
+        return;
+    };
+    let source = env.lavfi_clip("source.mp4", 1.0, true);
+    let output = env.dir.join("missing-folder").join("out.mp4");
+
+    let request = env.request(vec![clip("a", &source, 0, 0.0, 0.0, 1.0)], &output);
+    let (outcome, events) = env.render(&request);
+
+    let ExportOutcome::Failed(failure) = outcome else {
+        panic!("expected a failure, got {:?}", outcome);
+    };
+    assert!(failure.log_path.is_some_and(|path| Path::new(&path).is_file()));
+    assert!(matches!(events.last(), Some(ExportEvent::Failed(failed)) if failed.failure.message == failure.message));
+}
@@ -0,0 +1,32 @@
+/**
+ * @typedef {'file_not_found' | 'unsupported_media' | 'incompatible_media' | 'unsupported_codec'
+ *   | 'ffmpeg_missing' | 'ffmpeg_failed' | 'invalid_project' | 'invalid_input' | 'io' | 'internal'} ErrorKind
+ */
+
+/**
+ * Error returned by every backend command
+ * @typedef {Object} ClipforgeError
+ * @property {ErrorKind} kind - What went wrong
+ * @property {string} message - Human-readable description
+ * @property {string | null} path - File the error is about
+ * @property {string | null} ffmpeg_stderr - FFmpeg's stderr, for FFmpeg failures
+ */
+
+/**
+ * Message to show for an error thrown by `invoke` or by frontend code
+ * @param {any} err
+ * @returns {string}
+ */
+export function errorMessage(err) {
+  return err?.message ?? String(err);
+}
+
+/**
+ * Whether an error is a backend error of the given kind
+ * @param {any} err
+ * @param {ErrorKind} kind
+ * @returns {err is ClipforgeError}
+ */
+export function isErrorKind(err, kind) {
+  return err?.kind === kind;
+}
@@ -1,5 +1,6 @@
 import { writable, get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 /**
  * @typedef {Object} Clip
@@ -8,17 +9,145 @@ import { invoke } from '@tauri-apps/api/core';
  * @property {string} path - File path
  * @property {number} duration - Duration in seconds
  * @property {string} resolution - Resolution string (e.g., "1920x1080")
+ * @property {'video' | 'audio' | 'image'} [media_kind] - Source kind (defaults to video)
  * @property {string} [codec] - Optional video codec
+ * @property {number} [width] - Coded width in pixels
+ * @property {number} [height] - Coded height in pixels
+ * @property {number} [avg_frame_rate] - Average frame rate (0 if unknown)
+ * @property {number} [real_frame_rate] - Base frame rate (0 if unknown)
+ * @property {boolean} [is_vfr] - Whether the frame rate is variable
+ * @property {number} [bit_rate] - Bits per second
+ * @property {string | null} [pix_fmt] - Pixel format (e.g., "yuv420p")
+ * @property {number} [rotation] - Clockwise display rotation in degrees (0, 90, 180, 270)
+ * @property {number} [sample_aspect_ratio] - Pixel aspect ratio (1 for square pixels)
+ * @property {string} [container] - Container format name
+ * @property {number} [file_size] - File size in bytes
+ * @property {string | null} [creation_time] - Creation time tag, if present
+ * @property {AudioStream[]} [audio_streams] - Audio streams in file order (empty if the file is silent)
  * @property {string} [thumbnail] - Optional thumbnail data URL (base64-encoded image)
  * @property {string} [filmstrip] - Optional filmstrip file path
  * @property {number} [filmstripFrameCount] - Number of frames in filmstrip (default: 20)
  */
 
+/**
+ * @typedef {Object} AudioStream
+ * @property {number} index - Position among the file's audio streams
+ * @property {string} codec - Audio codec name
+ * @property {number} sample_rate - Sample rate in Hz
+ * @property {number} channels - Channel count
+ * @property {string | null} channel_layout - Layout name (e.g., "stereo", "5.1")
+ * @property {number} bit_rate - Bits per second
+ * @property {string | null} language - Language tag (e.g., "eng")
+ * @property {string | null} title - Stream title, if any
+ * @property {number} duration - Duration in seconds
+ * @property {boolean} is_default - Whether the file marks this stream as default
+ */
+
 /** @type {import('svelte/store').Writable<Clip[]>} */
 export const clipsStore = writable(
   /** @type {Clip[]} */ []
 );
 
+/**
+ * Seconds a still image lasts when imported
+ * @type {import('svelte/store').Writable<number>}
+ */
+export const imageDurationStore = writable(5);
+
+/**
+ * Where to grab a clip's thumbnail from
+ * @param {Clip} clip
+ * @returns {number | null} Timestamp in seconds, or null when the clip has no picture
+ */
+export function thumbnailTimestamp(clip) {
+  if (clip.media_kind === 'audio') return null;
+  if (clip.media_kind === 'image') return 0;
+  return Math.min(1.0, clip.duration ?? 1.0);
+}
+
+/**
+ * @typedef {Object} ImportIssue
+ * @property {string} path - File that was not imported
+ * @property {string} reason - Why it was skipped or failed
+ */
+
+/**
+ * @typedef {Object} ImportReport
+ * @property {Clip[]} imported - Metadata of the imported files
+ * @property {ImportIssue[]} errors - Supported files that could not be probed
+ * @property {ImportIssue[]} skipped - Unsupported files and missing paths
+ */
+
+/**
+ * Progress of the running batch import, null when idle
+ * @type {import('svelte/store').Writable<{ completed: number, total: number } | null>}
+ */
+export const importProgressStore = writable(
+  /** @type {{ completed: number, total: number } | null} */ (null)
+);
+
+/**
+ * Import files and folders (searched recursively) into the media library
+ * Thumbnails and filmstrips are generated in the background
+ * @param {string[]} paths - Files and folders to import
+ * @returns {Promise<ImportReport>}
+ */
+export async function importMediaPaths(paths) {
+  importProgressStore.set({ completed: 0, total: 0 });
+  const unlisten = await listen('import_progress', (event) => {
+    const { completed, total } = /** @type {{ completed: number, total: number }} */ (event.payload);
+    importProgressStore.set({ completed, total });
+  });
+
+  try {
+    const report = /** @type {ImportReport} */ (await invoke('import_media_paths', {
+      paths,
+      imageDuration: get(imageDurationStore)
+    }));
+
+    const newClips = report.imported.map((metadata, i) => ({
+      ...metadata,
+      id: `clip-${Date.now()}-${i}`
+    }));
+    clipsStore.update(clips => [...clips, ...newClips]);
+
+    for (const clip of newClips) {
+      generateThumbnailForClip(clip).catch(err => {
+        console.error("Error generating thumbnail for", clip.path, ":", err);
+      });
+      generateFilmstripForClip(clip.id).catch(err => {
+        console.error("Error generating filmstrip:", err);
+      });
+    }
+
+    for (const issue of [...report.errors, ...report.skipped]) {
+      console.warn(`Not imported: ${issue.path} (${issue.reason})`);
+    }
+    return report;
+  } finally {
+    unlisten();
+    importProgressStore.set(null);
+  }
+}
+
+/**
+ * Generate a thumbnail for a clip (none for audio)
+ * @param {Clip} clip
+ * @returns {Promise<void>}
+ */
+export async function generateThumbnailForClip(clip) {
+  const timestamp = thumbnailTimestamp(clip);
+  if (timestamp === null) return;
+
+  const thumbnail = await invoke('generate_thumbnail', {
+    videoPath: clip.path,
+    timestamp
+  });
+  clipsStore.update(clips =>
+    clips.map(c => (c.id === clip.id ? { ...c, thumbnail: String(thumbnail) } : c))
+  );
+}
+
 /**
  * Generate filmstrip for a clip (if not already generated)
  * @param {string} clipId - The clip ID to generate filmstrip for
@@ -34,6 +163,11 @@ export async function generateFilmstripForClip(clipId, frameCount = 20) {
     return;
   }
 
+  // Audio has no frames and an image would repeat the same frame
+  if (clip.media_kind === 'audio' || clip.media_kind === 'image') {
+    return;
+  }
+
   // Skip if filmstrip already exists
   if (clip.filmstrip) {
     console.log(`Filmstrip already exists for clip ${clipId}`);
@@ -0,0 +1,290 @@
+import { writable, get } from 'svelte/store';
+import { invoke } from '@tauri-apps/api/core';
+import { clipsStore, generateThumbnailForClip, generateFilmstripForClip } from './clips.js';
+import { timelineStore } from './timeline.js';
+
+/**
+ * @typedef {Object} ExportSettings
+ * @property {string} format - "mp4", "webm", or "mov"
+ * @property {string} resolution - "Source", "720p", "1080p", "1440p", or "4K"
+ * @property {string | null} outputPath - Last export destination
+ * @property {string | null} videoCodec - Video encoder override
+ * @property {string | null} audioCodec - Audio encoder override
+ */
+
+/**
+ * @typedef {Object} Marker
+ * @property {string} id - Unique marker ID
+ * @property {number} time - Position on timeline in seconds
+ * @property {string} label - Marker text
+ * @property {string | null} color - CSS color, null for the default
+ */
+
+/** Extension of ClipForge project files */
+export const PROJECT_EXTENSION = 'clipforge';
+
+/** @type {import('svelte/store').Writable<ExportSettings>} */
+export const exportSettingsStore = writable({
+  format: 'mp4',
+  resolution: '1080p',
+  outputPath: /** @type {string | null} */ (null),
+  videoCodec: /** @type {string | null} */ (null),
+  audioCodec: /** @type {string | null} */ (null)
+});
+
+/** @type {import('svelte/store').Writable<Marker[]>} */
+export const markersStore = writable(/** @type {Marker[]} */ ([]));
+
+/**
+ * @typedef {Object} MissingMedia
+ * @property {string} id - Media library clip ID
+ * @property {string} path - Path the file used to be at
+ * @property {string} filename - Original filename
+ * @property {number} duration - Duration in seconds
+ * @property {number} file_size - Size in bytes (0 if unknown)
+ */
+
+/**
+ * Media library clips whose files could not be found
+ * @type {import('svelte/store').Writable<MissingMedia[]>}
+ */
+export const missingMediaStore = writable(/** @type {MissingMedia[]} */ ([]));
+
+/**
+ * Path of the open project file, null until the project is first saved
+ * @type {import('svelte/store').Writable<string | null>}
+ */
+export const projectPathStore = writable(/** @type {string | null} */ (null));
+
+/**
+ * Build the backend project from the stores
+ * Thumbnails and filmstrips are caches and are not saved
+ */
+export function buildProject() {
+  const settings = get(exportSettingsStore);
+  return {
+    media: get(clipsStore).map(toProjectMedia),
+    clips: get(timelineStore).clips.map((clip) => {
+      const c = /** @type {any} */ (clip);
+      return {
+        id: c.id,
+        clip_id: c.clipId,
+        track: c.track,
+        start_time: c.startTime,
+        trim_start: c.trimStart,
+        trim_end: c.trimEnd,
+        duration: c.duration,
+        transform: c.transform ?? null,
+        audio_stream: c.audioStream ?? null
+      };
+    }),
+    export_settings: {
+      format: settings.format,
+      resolution: settings.resolution,
+      output_path: settings.outputPath,
+      video_codec: settings.videoCodec,
+      audio_codec: settings.audioCodec
+    },
+    markers: get(markersStore)
+  };
+}
+
+/**
+ * Media library clip as a project media entry
+ * @param {any} clip
+ */
+function toProjectMedia({ thumbnail, filmstrip, filmstripFrameCount, ...metadata }) {
+  return metadata;
+}
+
+/**
+ * Replace the stores with a project loaded by the backend
+ * @param {any} project - Project returned by `load_project`
+ * @param {MissingMedia[]} missing - Media whose files are gone
+ */
+export function applyProject(project, missing) {
+  clipsStore.set(project.media);
+  missingMediaStore.set(missing);
+
+  const clips = project.clips.map((/** @type {any} */ c) => ({
+    id: c.id,
+    clipId: c.clip_id,
+    track: c.track,
+    startTime: c.start_time,
+    trimStart: c.trim_start,
+    trimEnd: c.trim_end,
+    duration: c.duration,
+    ...(c.transform ? { transform: c.transform } : {}),
+    ...(c.audio_stream !== null && c.audio_stream !== undefined ? { audioStream: c.audio_stream } : {})
+  }));
+  timelineStore.set({
+    clips,
+    playhead: 0,
+    duration: clips.reduce((end, /** @type {any} */ c) => Math.max(end, c.startTime + c.duration), 0)
+  });
+
+  const settings = project.export_settings;
+  exportSettingsStore.set({
+    format: settings.format,
+    resolution: settings.resolution,
+    outputPath: settings.output_path ?? null,
+    videoCodec: settings.video_codec ?? null,
+    audioCodec: settings.audio_codec ?? null
+  });
+  markersStore.set(project.markers);
+
+  const missingIds = new Set(missing.map((m) => m.id));
+  for (const clip of project.media.filter((/** @type {any} */ c) => !missingIds.has(c.id))) {
+    generateThumbnailForClip(clip).catch(err => {
+      console.error("Error generating thumbnail for", clip.path, ":", err);
+    });
+    generateFilmstripForClip(clip.id).catch(err => {
+      console.error("Error generating filmstrip:", err);
+    });
+  }
+}
+
+/**
+ * Save the project to a file
+ * @param {string} path - Project file path
+ * @returns {Promise<void>}
+ */
+export async function saveProject(path) {
+  await invoke('save_project', { path, project: buildProject() });
+  projectPathStore.set(path);
+}
+
+/**
+ * Open a project file, replacing the current media library and timeline
+ * @param {string} path - Project file path
+ * @returns {Promise<void>}
+ */
+export async function loadProject(path) {
+  const { project, missing } = /** @type {any} */ (await invoke('load_project', { path }));
+  applyProject(project, missing);
+  projectPathStore.set(path);
+}
+
+/**
+ * Swap relinked media entries into the media library
+ * @param {any[]} relinked - Project media entries returned by the backend
+ */
+function applyRelinked(relinked) {
+  const byId = new Map(relinked.map((media) => [media.id, media]));
+  clipsStore.update(clips => clips.map(c => (byId.has(c.id) ? byId.get(c.id) : c)));
+  missingMediaStore.update(missing => missing.filter((m) => !byId.has(m.id)));
+  for (const media of relinked) {
+    generateThumbnailForClip(media).catch(err => {
+      console.error("Error generating thumbnail for", media.path, ":", err);
+    });
+  }
+}
+
+/**
+ * Flag the media library clips using a file as missing, e.g. when an export reports
+ * that the file is gone, so they can be relinked
+ * @param {string} path - File that could not be found
+ */
+export function markMediaMissing(path) {
+  const missingIds = new Set(get(missingMediaStore).map((m) => m.id));
+  const clips = get(clipsStore).filter((c) => c.path === path && !missingIds.has(c.id));
+  if (clips.length === 0) return;
+  missingMediaStore.update((missing) => [
+    ...missing,
+    ...clips.map((c) => ({
+      id: c.id,
+      path: c.path,
+      filename: c.filename,
+      duration: c.duration,
+      file_size: c.file_size ?? 0
+    }))
+  ]);
+}
+
+/**
+ * Point a missing clip at a replacement file
+ * The backend rejects files that don't match the original's kind and duration
+ * @param {string} clipId - Media library clip ID
+ * @param {string} path - Replacement file
+ * @returns {Promise<void>}
+ */
+export async function relinkClip(clipId, path) {
+  const clip = get(clipsStore).find((c) => c.id === clipId);
+  if (!clip) return;
+  const relinked = await invoke('relink_media', { media: toProjectMedia(clip), path });
+  applyRelinked([relinked]);
+}
+
+/**
+ * Search a folder for replacements of every missing clip
+ * @param {string} root - Folder to search recursively
+ * @returns {Promise<{ relinked: any[], unresolved: { id: string, reason: string }[] }>}
+ */
+export async function relinkFolder(root) {
+  const missingIds = new Set(get(missingMediaStore).map((m) => m.id));
+  const media = get(clipsStore).filter((c) => missingIds.has(c.id)).map(toProjectMedia);
+  const report = /** @type {any} */ (await invoke('relink_media_folder', { media, root }));
+  applyRelinked(report.relinked);
+  return report;
+}
+
+/**
+ * Collect the project and its media into a folder, or a zip if `destination` ends in .zip
+ * @param {string} destination - Folder to create or zip file to write
+ * @param {boolean} trimMedia - Keep only the used parts of each file (plus handles)
+ * @returns {Promise<{ path: string, media_files: number, total_bytes: number }>}
+ */
+export async function archiveProject(destination, trimMedia) {
+  return invoke('archive_project', {
+    project: buildProject(),
+    options: {
+      destination,
+      zip: /\.zip$/i.test(destination),
+      trim_media: trimMedia
+    }
+  });
+}
+
+/** Seconds between autosave snapshots */
+export const AUTOSAVE_INTERVAL = 30;
+
+/**
+ * Snapshot the project periodically for crash recovery
+ * The backend skips snapshots of unchanged projects
+ * @returns {() => void} Stops autosaving
+ */
+export function startAutosave() {
+  const timer = setInterval(async () => {
+    if (get(clipsStore).length === 0) return;
+    try {
+      await invoke('autosave_project', {
+        project: buildProject(),
+        projectPath: get(projectPathStore)
+      });
+    } catch (err) {
+      console.error('Autosave failed:', err);
+    }
+  }, AUTOSAVE_INTERVAL * 1000);
+  return () => clearInterval(timer);
+}
+
+/**
+ * Offer to restore the newest autosave snapshot after a crash or webview reload
+ * @returns {Promise<void>}
+ */
+export async function offerRecovery() {
+  const snapshot = /** @type {any} */ (await invoke('check_recovery'));
+  if (!snapshot) return;
+
+  const savedAt = new Date(snapshot.saved_at).toLocaleString();
+  const name = snapshot.project_path ?? 'an unsaved project';
+  if (confirm(`ClipForge found unsaved work in ${name} from ${savedAt}. Restore it?`)) {
+    const missing = /** @type {MissingMedia[]} */ (
+      await invoke('find_missing_media', { project: snapshot.project })
+    );
+    applyProject(snapshot.project, missing);
+    projectPathStore.set(snapshot.project_path);
+  } else {
+    await invoke('discard_recovery');
+  }
+}
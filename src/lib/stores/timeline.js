@@ -9,6 +9,7 @@ import { writable } from 'svelte/store';
  * @property {number} trimStart - Trim in point in seconds
  * @property {number} trimEnd - Trim out point in seconds
  * @property {number} duration - Duration in seconds
+ * @property {number} [audioStream] - Source audio stream to use (index into the clip's audio_streams)
  */
 
 /**